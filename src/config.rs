@@ -0,0 +1,163 @@
+use clap::{Parser, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::utils::token_registry::{
+    TokenListConfig, TokenListSource, UNISWAP_TOKEN_LIST_URL, default_cache_dir,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Transport {
+    /// Serve a single MCP client over standard input/output.
+    Stdio,
+    /// Serve many MCP clients over streamable HTTP (with SSE streaming).
+    Http,
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Ethereum trading MCP server")]
+pub struct Cli {
+    /// Transport used to talk to MCP clients.
+    #[arg(long, value_enum, default_value_t = Transport::Stdio, env = "MCP_TRANSPORT")]
+    pub transport: Transport,
+
+    /// Socket address to listen on when using the HTTP transport.
+    #[arg(long, default_value = "127.0.0.1:8080", env = "MCP_BIND")]
+    pub bind: SocketAddr,
+
+    /// Token lists (URLs or local JSON files) used to resolve symbols, highest precedence first.
+    #[arg(
+        long = "token-list",
+        value_name = "URL_OR_PATH",
+        value_delimiter = ',',
+        default_value = UNISWAP_TOKEN_LIST_URL,
+        env = "TOKEN_LISTS"
+    )]
+    pub token_lists: Vec<TokenListSource>,
+
+    /// Token list file whose entries take precedence over every other list.
+    #[arg(long, value_name = "PATH", env = "TOKEN_OVERRIDES")]
+    pub token_overrides: Option<PathBuf>,
+
+    /// Never fetch remote token lists; use local lists and the bundled list only.
+    #[arg(long, env = "TOKEN_LIST_OFFLINE")]
+    pub offline: bool,
+
+    /// Directory caching downloaded token lists [default: ~/.cache/eth_mcp_server].
+    #[arg(long, env = "TOKEN_LIST_CACHE_DIR")]
+    pub token_list_cache_dir: Option<PathBuf>,
+
+    /// Seconds before a cached token list is revalidated.
+    #[arg(long, default_value_t = 86400, env = "TOKEN_LIST_TTL")]
+    pub token_list_ttl: u64,
+
+    /// JSON file of named accounts (private keys, encrypted keystores or mnemonics) tools can sign
+    /// with, in addition to ETH_PRIVATE_KEY as the 'default' account.
+    #[arg(long, value_name = "PATH", env = "ACCOUNTS_FILE")]
+    pub accounts: Option<PathBuf>,
+
+    /// Allow tools to sign and broadcast transactions with the configured wallet.
+    #[arg(long, env = "ALLOW_BROADCAST")]
+    pub allow_broadcast: bool,
+
+    /// JSON spending policy checked before any transaction is signed; required with
+    /// --allow-broadcast.
+    #[arg(long, value_name = "PATH", env = "SPENDING_POLICY")]
+    pub policy: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn token_list_config(&self) -> TokenListConfig {
+        TokenListConfig {
+            sources: self.token_lists.clone(),
+            overrides: self.token_overrides.clone(),
+            offline: self.offline,
+            cache_dir: self.token_list_cache_dir.clone().or_else(default_cache_dir),
+            ttl: Duration::from_secs(self.token_list_ttl),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_defaults_to_stdio() {
+        let cli = Cli::try_parse_from(["eth_mcp_server"]).unwrap();
+        assert_eq!(cli.transport, Transport::Stdio);
+    }
+
+    #[test]
+    fn cli_parses_http_transport_and_bind() {
+        let cli =
+            Cli::try_parse_from(["eth_mcp_server", "--transport", "http", "--bind", "0.0.0.0:9000"])
+                .unwrap();
+        assert_eq!(cli.transport, Transport::Http);
+        assert_eq!(cli.bind, "0.0.0.0:9000".parse::<SocketAddr>().unwrap());
+    }
+
+    #[test]
+    fn cli_defaults_to_uniswap_token_list() {
+        let config = Cli::try_parse_from(["eth_mcp_server"]).unwrap().token_list_config();
+        assert_eq!(config.sources, vec![TokenListSource::Url(UNISWAP_TOKEN_LIST_URL.to_string())]);
+        assert_eq!(config.overrides, None);
+    }
+
+    #[test]
+    fn cli_parses_token_list_options() {
+        let cli = Cli::try_parse_from([
+            "eth_mcp_server",
+            "--token-list",
+            "lists/mine.json,https://example.com/tokens.json",
+            "--token-overrides",
+            "overrides.json",
+            "--offline",
+            "--token-list-cache-dir",
+            "/tmp/tokens",
+            "--token-list-ttl",
+            "60",
+        ])
+        .unwrap();
+        let config = cli.token_list_config();
+        assert_eq!(
+            config.sources,
+            vec![
+                TokenListSource::File(PathBuf::from("lists/mine.json")),
+                TokenListSource::Url("https://example.com/tokens.json".to_string()),
+            ]
+        );
+        assert_eq!(config.overrides, Some(PathBuf::from("overrides.json")));
+        assert!(config.offline);
+        assert_eq!(config.cache_dir, Some(PathBuf::from("/tmp/tokens")));
+        assert_eq!(config.ttl, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn cli_broadcast_is_opt_in() {
+        assert!(!Cli::try_parse_from(["eth_mcp_server"]).unwrap().allow_broadcast);
+        let cli = Cli::try_parse_from(["eth_mcp_server", "--allow-broadcast"]).unwrap();
+        assert!(cli.allow_broadcast);
+    }
+
+    #[test]
+    fn cli_parses_accounts_file() {
+        assert_eq!(Cli::try_parse_from(["eth_mcp_server"]).unwrap().accounts, None);
+        let cli = Cli::try_parse_from(["eth_mcp_server", "--accounts", "accounts.json"]).unwrap();
+        assert_eq!(cli.accounts, Some(PathBuf::from("accounts.json")));
+    }
+
+    #[test]
+    fn cli_parses_policy() {
+        assert_eq!(Cli::try_parse_from(["eth_mcp_server"]).unwrap().policy, None);
+        let cli = Cli::try_parse_from(["eth_mcp_server", "--policy", "policy.json"]).unwrap();
+        assert_eq!(cli.policy, Some(PathBuf::from("policy.json")));
+    }
+
+    #[test]
+    fn cli_rejects_invalid_bind() {
+        let result = Cli::try_parse_from(["eth_mcp_server", "--bind", "not-an-address"]);
+        assert!(result.is_err());
+    }
+}
@@ -1,13 +1,29 @@
-use anyhow::Result;
-use rmcp::{ServiceExt, transport::stdio};
+use anyhow::{Result, bail};
+use clap::Parser;
+use config::{Cli, Transport};
+use rmcp::{
+    ServiceExt,
+    transport::{
+        stdio,
+        streamable_http_server::{StreamableHttpService, session::local::LocalSessionManager},
+    },
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tools::router::EthTools;
 use tracing_subscriber::{self, EnvFilter};
+use utils::accounts::Accounts;
+use utils::policy::Policy;
+use utils::provider::Providers;
 
+mod config;
 mod tools;
 mod utils;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
     // Initialize the tracing subscriber with file and stdout logging
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
@@ -16,12 +32,73 @@ async fn main() -> Result<()> {
         .init();
 
     tracing::info!("Starting MCP server");
+    utils::token_registry::configure(cli.token_list_config());
+
+    // Connect to the RPC endpoints once, failing fast on misconfiguration.
+    let providers = Providers::connect().await?;
+    // Decrypt keystores and derive mnemonic keys once, failing fast on a bad password or path.
+    let accounts = Accounts::load(cli.accounts.as_deref())?;
+    for (name, address) in accounts.addresses() {
+        tracing::info!("Loaded account '{}' ({})", name, address);
+    }
+
+    // Signing tools are only served under a spending policy.
+    let policy = match &cli.policy {
+        Some(path) => Policy::load(path).await?,
+        None if cli.allow_broadcast => bail!("--allow-broadcast requires a spending policy; pass --policy"),
+        None => Policy::default(),
+    };
+    if cli.allow_broadcast {
+        tracing::warn!("Broadcasting is enabled: tools may sign and send transactions");
+    }
+    let policy = Arc::new(policy);
+
+    match cli.transport {
+        Transport::Stdio => serve_stdio(providers, accounts, cli.allow_broadcast, policy).await,
+        Transport::Http => serve_http(providers, accounts, cli.allow_broadcast, policy, cli.bind).await,
+    }
+}
 
+async fn serve_stdio(
+    providers: Providers,
+    accounts: Accounts,
+    allow_broadcast: bool,
+    policy: Arc<Policy>,
+) -> Result<()> {
     // Create an instance of our counter router
-    let service = EthTools::new().serve(stdio()).await.inspect_err(|e| {
+    let tools = EthTools::new(providers, accounts, allow_broadcast, policy);
+    let service = tools.serve(stdio()).await.inspect_err(|e| {
         tracing::error!("Serving error: {:?}", e);
     })?;
 
     service.waiting().await?;
     Ok(())
 }
+
+async fn serve_http(
+    providers: Providers,
+    accounts: Accounts,
+    allow_broadcast: bool,
+    policy: Arc<Policy>,
+    bind: SocketAddr,
+) -> Result<()> {
+    // Every MCP session gets its own `EthTools` handle, all sharing the same providers, accounts
+    // and spending policy, so daily caps hold across sessions.
+    let service = StreamableHttpService::new(
+        move || Ok(EthTools::new(providers.clone(), accounts.clone(), allow_broadcast, policy.clone())),
+        LocalSessionManager::default().into(),
+        Default::default(),
+    );
+
+    let router = axum::Router::new().nest_service("/mcp", service);
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    tracing::info!("Serving MCP over streamable HTTP at http://{}/mcp", listener.local_addr()?);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutting down HTTP transport");
+        })
+        .await?;
+    Ok(())
+}
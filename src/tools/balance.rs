@@ -1,23 +1,30 @@
-#![allow(dead_code)]
-
+use crate::utils::block::{PinnedBlock, block_id, pin_block};
+use crate::utils::chains::resolve_chain;
 use crate::utils::contracts::IERC20;
 use crate::utils::decimals::u256_to_decimal;
-use crate::utils::provider::make_provider;
-use crate::utils::token_registry::resolve_token;
-use alloy::primitives::Address;
+use crate::utils::provider::Providers;
+use crate::utils::ens::{resolve_address, resolve_token_or_name};
+use crate::utils::token_registry::token_metadata;
 use alloy::providers::Provider;
 use anyhow::{Context, Result};
 use rust_decimal::Decimal;
-use std::str::FromStr;
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct BalanceRequest {
-    #[schemars(description = "Wallet address (e.g., '0x...')")]
+    #[schemars(description = "Wallet address (e.g., '0x...') or ENS name (e.g., 'vitalik.eth')")]
     pub wallet_address: String,
     #[schemars(
         description = "Token symbol (e.g., 'UNI') or address (e.g., '0x...'); If not provided, the balance of the native asset will be returned"
     )]
     pub token: Option<String>,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+    #[schemars(
+        description = "Block to query at: number (e.g., '19000000'), hash, tag (e.g., 'finalized') or ISO 8601 timestamp (e.g., '2024-01-01T00:00:00Z'); defaults to latest. Past blocks need an archive node"
+    )]
+    pub block: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -25,45 +32,58 @@ pub struct BalanceResponse {
     // Serialize as string to avoid precision loss.
     #[serde(with = "rust_decimal::serde::str")]
     pub balance: Decimal,
+    /// Block the balance was read at; absent when reading the latest state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<PinnedBlock>,
 }
 
-pub async fn get_balance(wallet_address: String, token: Option<String>) -> Result<BalanceResponse> {
+pub async fn get_balance(
+    providers: &Providers,
+    wallet_address: String,
+    token: Option<String>,
+    chain: Option<String>,
+    block: Option<String>,
+) -> Result<BalanceResponse> {
+    let chain = resolve_chain(chain.as_deref())?;
+
     tracing::trace!("Parsing wallet address: {}", wallet_address);
-    let wallet_address = Address::from_str(&wallet_address)
+    let wallet_address = resolve_address(providers, chain, &wallet_address)
+        .await
         .context(format!("Invalid wallet address: {}", wallet_address))?;
-    
-    tracing::trace!("Creating provider");
-    let provider = make_provider()?;
+
+    tracing::trace!("Using provider for chain: {}", chain.name);
+    let provider = providers.get(chain)?;
+    let block = pin_block(provider, block.as_deref()).await?;
+    let block_id = block_id(block.as_ref());
 
     match token {
         None => {
-            tracing::debug!("Fetching ETH balance for address: {}", wallet_address);
+            tracing::debug!("Fetching {} balance for address: {}", chain.native_symbol, wallet_address);
             let balance = provider
                 .get_balance(wallet_address)
+                .block_id(block_id)
                 .await
-                .context("Failed to get ETH balance")?;
+                .context(format!("Failed to get {} balance", chain.native_symbol))?;
             tracing::trace!("ETH balance retrieved: {} wei", balance);
             Ok(BalanceResponse {
                 balance: u256_to_decimal(balance, 18)?,
+                block,
             })
         }
         Some(token_str) => {
             tracing::debug!("Fetching {} balance for address: {}", token_str, wallet_address);
-            let token_address = resolve_token(&token_str).await?;
+            let token_address = resolve_token_or_name(providers, chain, &token_str).await?;
             tracing::trace!("Token resolved to address: {}", token_address);
             
-            let contract = IERC20::new(token_address, &provider);
+            let contract = IERC20::new(token_address, provider);
 
             tracing::trace!("Fetching token decimals");
-            let decimals = contract
-                .decimals()
-                .call()
-                .await
-                .context("Failed to call decimals")?;
+            let decimals = token_metadata(provider, chain.chain_id, token_address).await?.decimals;
             tracing::trace!("Token decimals: {}", decimals);
             
             let balance = contract
                 .balanceOf(wallet_address)
+                .block(block_id)
                 .call()
                 .await
                 .context("Failed to call balanceOf")?;
@@ -71,6 +91,7 @@ pub async fn get_balance(wallet_address: String, token: Option<String>) -> Resul
 
             Ok(BalanceResponse {
                 balance: u256_to_decimal(balance, decimals)?,
+                block,
             })
         }
     }
@@ -85,16 +106,38 @@ mod tests {
 
     #[tokio::test]
     async fn get_balance_invalid_wallet_returns_error() {
-        let result = get_balance("not-a-valid-address".to_string(), None).await;
+        let result = get_balance(
+            &Providers::default(),
+            "not-a-valid-address".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn get_balance_unknown_chain_returns_error() {
+        let result = get_balance(
+            &Providers::default(),
+            "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".to_string(),
+            None,
+            Some("not-a-chain".to_string()),
+            None,
+        )
+        .await;
+        let err = result.err().expect("unknown chain should fail");
+        assert!(err.to_string().contains("Unsupported chain"));
+    }
+
     #[test]
     fn balance_response_serde_uses_string_field() {
         let balance = Decimal::from_str("1234.5678").unwrap();
-        let response = BalanceResponse { balance };
+        let response = BalanceResponse { balance, block: None };
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"balance\":\"1234.5678\""));
+        assert!(!json.contains("block"));
 
         let parsed: BalanceResponse = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.balance, response.balance);
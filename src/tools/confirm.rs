@@ -0,0 +1,49 @@
+use anyhow::{Result, bail};
+use rmcp::service::{ElicitationError, Peer, RoleServer};
+use std::time::Duration;
+
+/// How long the client user has to answer a confirmation; well within the swap's deadline.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Answer the client user gives to a confirmation request.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct Confirmation {
+    #[schemars(description = "Sign the permit and transactions described above and broadcast the transactions")]
+    pub confirm: bool,
+}
+
+rmcp::elicit_safe!(Confirmation);
+
+/// Show `summary` to the client user through MCP elicitation and succeed only if they confirm it.
+/// Clients that do not support elicitation are refused, so nothing is signed without a human.
+pub async fn confirm_with_user(peer: &Peer<RoleServer>, summary: String) -> Result<()> {
+    tracing::debug!("Asking the client user to confirm:\n{}", summary);
+    match peer.elicit_with_timeout::<Confirmation>(summary, Some(CONFIRMATION_TIMEOUT)).await {
+        Ok(Some(Confirmation { confirm: true })) => {
+            tracing::info!("The client user confirmed the transaction");
+            Ok(())
+        }
+        Ok(_) | Err(ElicitationError::UserDeclined) | Err(ElicitationError::UserCancelled) => {
+            bail!("The client user did not confirm the transaction; nothing was signed")
+        }
+        Err(ElicitationError::CapabilityNotSupported) => {
+            bail!("Refusing to sign: the MCP client does not support elicitation, so the transaction cannot be confirmed by a human")
+        }
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to ask the client user for confirmation")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ElicitationSchema;
+
+    #[test]
+    fn confirmation_schema_is_a_single_boolean() {
+        let schema = ElicitationSchema::from_type::<Confirmation>().unwrap();
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["type"], "object");
+        assert_eq!(json["properties"]["confirm"]["type"], "boolean");
+        assert_eq!(json["required"], serde_json::json!(["confirm"]));
+    }
+}
@@ -0,0 +1,61 @@
+use alloy::primitives::Address;
+use anyhow::{Context, Result};
+use std::str::FromStr;
+
+use crate::utils::chains::resolve_chain;
+use crate::utils::ens::reverse_resolve;
+use crate::utils::provider::Providers;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ReverseResolveRequest {
+    #[schemars(description = "Address to look up (e.g., '0x...')")]
+    pub address: String,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'sepolia') or chain id; chains without ENS use Ethereum mainnet; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReverseResolveResponse {
+    pub address: Address,
+    /// Primary ENS name, absent when the address has none or it does not resolve back to the address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+pub async fn reverse_resolve_address(
+    providers: &Providers,
+    address: String,
+    chain: Option<String>,
+) -> Result<ReverseResolveResponse> {
+    let address =
+        Address::from_str(address.trim()).context(format!("Invalid address: {}", address))?;
+    let chain = resolve_chain(chain.as_deref())?;
+
+    let name = reverse_resolve(providers, chain, address).await?;
+    Ok(ReverseResolveResponse { address, name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reverse_resolve_invalid_address_returns_error() {
+        let result =
+            reverse_resolve_address(&Providers::default(), "vitalik.eth".to_string(), None).await;
+        let err = result.expect_err("names are not addresses");
+        assert!(err.to_string().contains("Invalid address"));
+    }
+
+    #[test]
+    fn reverse_resolve_response_omits_missing_name() {
+        let response = ReverseResolveResponse {
+            address: Address::ZERO,
+            name: None,
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(!json.contains("name"));
+    }
+}
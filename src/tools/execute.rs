@@ -0,0 +1,614 @@
+use alloy::network::{Ethereum, TransactionBuilder};
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{PendingTransactionBuilder, Provider};
+use alloy::rpc::types::{Log, TransactionReceipt, TransactionRequest};
+use anyhow::{Context, Result, bail};
+use chrono::DateTime;
+use rust_decimal::Decimal;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::tools::swap::{
+    SimulatedSwap, SwapMode, SwapRequest, SwapResponse, SwapRouter, simulate_swap,
+};
+use crate::tools::transaction::{Approval, TransactionKind, permit2_permit, required_approvals};
+use crate::utils::chains::{ChainProfile, resolve_chain};
+use crate::utils::contracts::IAllowanceTransfer::PermitSingle;
+use crate::utils::contracts::{IERC20, WETH9};
+use crate::utils::decimals::u256_to_decimal;
+use crate::utils::gas::NATIVE_DECIMALS;
+use crate::utils::policy::{Policy, PolicyToken, Spend};
+use crate::utils::accounts::Accounts;
+use crate::utils::provider::Providers;
+
+/// How long to wait for a sent transaction to be mined.
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(180);
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExecuteSwapRequest {
+    #[schemars(
+        description = "From token symbol (e.g., 'USDC') or address (e.g., '0x...'); the native symbol (e.g., 'ETH') swaps the native asset"
+    )]
+    pub from_token: String,
+    #[schemars(
+        description = "To token symbol (e.g., 'WETH') or address (e.g., '0x...'); the native symbol (e.g., 'ETH') receives the native asset"
+    )]
+    pub to_token: String,
+    #[schemars(
+        description = "Amount to swap from in formatted string format (e.g., '100.5'); required for exact_input"
+    )]
+    // String is used to avoid precision loss.
+    pub amount_from: Option<String>,
+    #[schemars(
+        description = "Exact amount to receive in formatted string format (e.g., '1.0'); required for exact_output"
+    )]
+    // String is used to avoid precision loss.
+    pub amount_to: Option<String>,
+    #[schemars(
+        description = "Swap mode: 'exact_input' or 'exact_output'; inferred from which amount is provided if omitted"
+    )]
+    pub mode: Option<SwapMode>,
+    #[schemars(description = "Slippage tolerance in percent as string format (e.g., '0.5')")]
+    // String is used to avoid precision loss.
+    pub slippage_percent: String,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+    #[schemars(
+        description = "Router to swap through: 'universal_router' (default, with Permit2) or 'venue_router'"
+    )]
+    pub router: Option<SwapRouter>,
+    #[schemars(
+        description = "Send an ERC20 approval first when the allowance is insufficient; defaults to true. When false, an insufficient allowance fails the call"
+    )]
+    pub approve: Option<bool>,
+    #[schemars(
+        description = "Name of the configured account to sign with; defaults to the 'default' account (or the only one)"
+    )]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ExecuteSwapResponse {
+    pub chain: String,
+    /// Configured account that signed the swap and received its output.
+    pub sender: Address,
+    /// Last approval sent before the swap; absent when the allowances already covered it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_transaction_hash: Option<B256>,
+    pub transaction_hash: B256,
+    pub block_number: u64,
+    pub gas_used: u64,
+    /// gas_used * effective gas price of the swap transaction, in the native asset.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub gas_cost_native: Decimal,
+    /// Amount actually spent, read from the receipt's logs.
+    // Serialize as string to avoid precision loss.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount_from: Decimal,
+    /// Amount actually received, read from the receipt's logs.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount_to: Decimal,
+    /// Simulation the transaction was built from: quote, limits, route, router and calldata.
+    pub simulation: SwapResponse,
+}
+
+/// Simulate a swap from a configured account and, once it passes `policy` and `confirm` accepts
+/// its summary, sign and send its approvals and the swap itself.
+pub async fn execute_swap<F, Fut>(
+    providers: &Providers,
+    accounts: &Accounts,
+    allow_broadcast: bool,
+    policy: &Policy,
+    confirm: F,
+    request: ExecuteSwapRequest,
+) -> Result<ExecuteSwapResponse>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    if !allow_broadcast {
+        bail!("Broadcasting transactions is disabled; start the server with --allow-broadcast to execute swaps");
+    }
+    let ExecuteSwapRequest {
+        from_token,
+        to_token,
+        amount_from,
+        amount_to,
+        mode,
+        slippage_percent,
+        chain,
+        router,
+        approve,
+        account,
+    } = request;
+
+    let sender = accounts.get(account.as_deref())?.context(
+        "Executing swaps needs a signer: set ETH_PRIVATE_KEY or an accounts file, and pick an account when several are configured",
+    )?;
+    let chain = resolve_chain(chain.as_deref())?;
+    let provider = providers.get(chain)?;
+
+    // Simulate from the signer at the latest block. Like any simulation, it injects the Permit2
+    // allowance instead of signing a permit.
+    tracing::debug!("Simulating swap {} -> {} from {}", from_token, to_token, sender);
+    let swap = simulate_swap(
+        providers,
+        accounts,
+        SwapRequest {
+            from_token,
+            to_token,
+            amount_from,
+            amount_to,
+            mode,
+            slippage_percent,
+            from_address: Some(sender.to_string()),
+            account: None,
+            chain: Some(chain.name.to_string()),
+            router,
+            block: None,
+            build_transaction: None,
+            signed_permit: None,
+        },
+    )
+    .await?;
+
+    // The policy judges the swap exactly as simulated and only then hands out the signer.
+    let spend = swap_spend(&swap).await?;
+    let signer = policy.authorize(accounts, sender, &spend)?;
+    ensure_balance(provider, &swap).await?;
+    // Universal Router swaps carry a permit instead of a Permit2 approval transaction.
+    let permit = permit2_permit(provider, &swap).await?;
+    let approvals = required_approvals(provider, &swap, permit.is_some()).await?;
+    if !approvals.is_empty() && !approve.unwrap_or(true) {
+        bail!(
+            "{} needs {} approval transaction(s) before the swap; set approve to send them",
+            sender,
+            approvals.len()
+        );
+    }
+    confirm(swap_summary(chain, sender, &spend, &swap.response, permit.as_ref(), &approvals)).await?;
+
+    // Nothing is signed before the swap is authorized and confirmed.
+    let calldata = match permit {
+        Some(permit) => {
+            let signature = signer.sign_permit(chain, &permit)?;
+            tracing::debug!("Signed Permit2 permit with nonce {}", permit.details.nonce);
+            swap.calldata_with_permit(permit, signature)?
+        }
+        None => swap.response.calldata.clone(),
+    };
+    let wallet = signer.wallet(provider);
+    let mut approval_transaction_hash = None;
+    for approval in approvals {
+        tracing::info!("Sending {} transaction to {} from {}", approval.kind.name(), approval.to, sender);
+        let receipt = send_and_confirm(&wallet, approval.request(sender), approval.kind.name()).await?;
+        approval_transaction_hash = Some(receipt.transaction_hash);
+    }
+
+    let simulation = &swap.response;
+    let tx = TransactionRequest::default()
+        .with_from(sender)
+        .with_to(simulation.router_address)
+        .with_input(calldata)
+        .with_value(simulation.value);
+    tracing::info!("Sending swap transaction to {} from {}", simulation.router_address, sender);
+    // A swap that fails to send gives its spending back as `signer` is dropped; once broadcast, it
+    // may still be mined, so its spending is kept even when no receipt confirms it.
+    let label = TransactionKind::Swap.name();
+    let pending = send(&wallet, tx, label).await?;
+    let hash = *pending.tx_hash();
+    let receipt = match wait_for_receipt(pending, label).await {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            tracing::warn!(
+                "Swap transaction {} was broadcast but not confirmed; its {} {} stay counted towards today's spending: {:#}",
+                hash,
+                spend.amount_in,
+                spend.token_in,
+                e
+            );
+            signer.confirm();
+            return Err(e);
+        }
+    };
+    signer.confirm();
+
+    let (spent, received) = settled_amounts(
+        receipt.inner.logs(),
+        chain.weth,
+        sender,
+        swap.token_in,
+        swap.token_out,
+    );
+    tracing::debug!("Swap {} spent {} and received {}", receipt.transaction_hash, spent, received);
+    let gas_cost = U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price);
+
+    Ok(ExecuteSwapResponse {
+        chain: chain.name.to_string(),
+        sender,
+        approval_transaction_hash,
+        transaction_hash: receipt.transaction_hash,
+        block_number: receipt.block_number.unwrap_or_default(),
+        gas_used: receipt.gas_used,
+        gas_cost_native: u256_to_decimal(gas_cost, NATIVE_DECIMALS)?,
+        amount_from: u256_to_decimal(spent, swap.from_decimals)?,
+        amount_to: u256_to_decimal(received, swap.to_decimals)?,
+        simulation: swap.response,
+    })
+}
+
+/// What a simulated swap spends, for the spending policy: its maximum input, the router it is sent
+/// to and the contract its input is approved to.
+async fn swap_spend(swap: &SimulatedSwap) -> Result<Spend> {
+    let (token_in, token_out) = tokio::try_join!(
+        PolicyToken::resolve(swap.chain, swap.token_in),
+        PolicyToken::resolve(swap.chain, swap.token_out),
+    )?;
+    Ok(Spend {
+        chain_id: swap.chain.chain_id,
+        token_in,
+        amount_in: u256_to_decimal(swap.max_input, swap.from_decimals)?,
+        token_out,
+        router: swap.response.router_address,
+        counterparties: swap.spender.into_iter().collect(),
+        slippage_percent: swap.slippage_percent,
+        price_impact_percent: swap.response.price_impact_percent,
+    })
+}
+
+/// Human-readable summary of everything `execute_swap` is about to sign, for the client user to
+/// confirm.
+fn swap_summary(
+    chain: &ChainProfile,
+    sender: Address,
+    spend: &Spend,
+    simulation: &SwapResponse,
+    permit: Option<&PermitSingle>,
+    approvals: &[Approval],
+) -> String {
+    let (token_in, token_out) = (&spend.token_in, &spend.token_out);
+    let mut lines = vec![format!("Swap on {} from {}", chain.name, sender)];
+    match simulation.mode {
+        SwapMode::ExactInput => {
+            lines.push(format!("Pay: {} {}", simulation.amount_from, token_in));
+            lines.push(format!(
+                "Receive: about {} {}, at least {}",
+                simulation.amount_to,
+                token_out,
+                simulation.amount_out_minimum.unwrap_or_default()
+            ));
+        }
+        SwapMode::ExactOutput => {
+            lines.push(format!(
+                "Pay: about {} {}, at most {}",
+                simulation.amount_from,
+                token_in,
+                simulation.amount_in_maximum.unwrap_or_default()
+            ));
+            lines.push(format!("Receive: {} {}", simulation.amount_to, token_out));
+        }
+    }
+    lines.push(format!("Recipient: {}", sender));
+    lines.push(format!("Price impact: {}%", simulation.price_impact_percent));
+    let gas = &simulation.gas_cost;
+    lines.push(match gas.cost_in_to_token {
+        Some(cost) => format!(
+            "Estimated gas cost: {} {} (about {} {})",
+            gas.cost_native, chain.native_symbol, cost, token_out
+        ),
+        None => format!("Estimated gas cost: {} {}", gas.cost_native, chain.native_symbol),
+    });
+    if gas.excludes_l1_data_fee {
+        lines.push(format!("Plus an L1 data fee on {}, not included above", chain.name));
+    }
+    lines.push(format!("Router: {}", simulation.router_address));
+    if let Some(permit) = permit {
+        let expiration = DateTime::from_timestamp(permit.details.expiration.to::<i64>(), 0).unwrap_or_default();
+        lines.push(format!(
+            "Signed first: Permit2 permit letting {} spend up to {} {} until {}",
+            permit.spender,
+            spend.amount_in,
+            token_in,
+            expiration.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    for approval in approvals {
+        lines.push(format!("Sent first: {} transaction to {}", approval.kind.name(), approval.to));
+    }
+    lines.join("\n")
+}
+
+/// Fail early, with a clear message, when the sender cannot pay the swap's maximum input.
+async fn ensure_balance(provider: &impl Provider<Ethereum>, swap: &SimulatedSwap) -> Result<()> {
+    let balance = match swap.token_in {
+        Some(token) => IERC20::new(token, provider)
+            .balanceOf(swap.sender)
+            .call()
+            .await
+            .context(format!("Failed to fetch balance of token {}", token))?,
+        None => provider
+            .get_balance(swap.sender)
+            .await
+            .context("Failed to fetch native balance")?,
+    };
+    if balance < swap.max_input {
+        bail!(
+            "Insufficient balance: {} holds {} but the swap may spend up to {}",
+            swap.sender,
+            u256_to_decimal(balance, swap.from_decimals)?,
+            u256_to_decimal(swap.max_input, swap.from_decimals)?
+        );
+    }
+    Ok(())
+}
+
+/// Sign and broadcast `tx`, wait for it to be mined and fail if it reverted.
+async fn send_and_confirm(
+    provider: &impl Provider<Ethereum>,
+    tx: TransactionRequest,
+    label: &str,
+) -> Result<TransactionReceipt> {
+    let pending = send(provider, tx, label).await?;
+    wait_for_receipt(pending, label).await
+}
+
+/// Sign and broadcast `tx`.
+async fn send(
+    provider: &impl Provider<Ethereum>,
+    tx: TransactionRequest,
+    label: &str,
+) -> Result<PendingTransactionBuilder<Ethereum>> {
+    let pending = provider
+        .send_transaction(tx)
+        .await
+        .context(format!("Failed to send {} transaction", label))?;
+    tracing::debug!("Sent {} transaction {}", label, pending.tx_hash());
+    Ok(pending)
+}
+
+/// Wait for a broadcast transaction to be mined and fail if it reverted.
+async fn wait_for_receipt(
+    pending: PendingTransactionBuilder<Ethereum>,
+    label: &str,
+) -> Result<TransactionReceipt> {
+    let hash = *pending.tx_hash();
+    let receipt = pending
+        .with_timeout(Some(RECEIPT_TIMEOUT))
+        .get_receipt()
+        .await
+        .context(format!("Failed to get the receipt of {} transaction {}", label, hash))?;
+    if !receipt.status() {
+        bail!("The {} transaction {} reverted", label, hash);
+    }
+    Ok(receipt)
+}
+
+/// Amounts `sender` spent and received in a swap, from its receipt's logs. ERC20 legs are read
+/// from `Transfer` events; native legs from the wrapped token's `Deposit` and `Withdrawal`, since
+/// routers wrap native input (refunding any excess by unwrapping) and unwrap native output.
+fn settled_amounts(
+    logs: &[Log],
+    weth: Address,
+    sender: Address,
+    token_in: Option<Address>,
+    token_out: Option<Address>,
+) -> (U256, U256) {
+    let (mut spent, mut received) = (U256::ZERO, U256::ZERO);
+    let (mut wrapped, mut unwrapped) = (U256::ZERO, U256::ZERO);
+    for log in logs {
+        if let Ok(transfer) = log.log_decode::<IERC20::Transfer>() {
+            let transfer = transfer.inner.data;
+            if Some(log.address()) == token_in && transfer.from == sender {
+                spent += transfer.value;
+            }
+            if Some(log.address()) == token_out && transfer.to == sender {
+                received += transfer.value;
+            }
+        } else if log.address() == weth {
+            if let Ok(deposit) = log.log_decode::<WETH9::Deposit>() {
+                wrapped += deposit.inner.data.wad;
+            } else if let Ok(withdrawal) = log.log_decode::<WETH9::Withdrawal>() {
+                unwrapped += withdrawal.inner.data.wad;
+            }
+        }
+    }
+
+    if token_in.is_none() {
+        spent = wrapped.saturating_sub(unwrapped);
+    }
+    if token_out.is_none() {
+        received = unwrapped;
+    }
+    (spent, received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::{LOCAL, MAINNET};
+    use crate::utils::universal_router::permit_single;
+    use alloy::primitives::LogData;
+    use alloy::primitives::aliases::U48;
+    use alloy::sol_types::SolEvent;
+
+    const SENDER: Address = Address::repeat_byte(0x11);
+    const POOL: Address = Address::repeat_byte(0x22);
+    const ROUTER: Address = Address::repeat_byte(0x33);
+
+    fn log(address: Address, data: LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log { address, data },
+            ..Default::default()
+        }
+    }
+
+    fn transfer(token: Address, from: Address, to: Address, value: u64) -> Log {
+        let event = IERC20::Transfer {
+            from,
+            to,
+            value: U256::from(value),
+        };
+        log(token, event.encode_log_data())
+    }
+
+    #[test]
+    fn test_settled_amounts_from_transfers() {
+        let usdc = MAINNET.usd_token;
+        let logs = vec![
+            transfer(usdc, SENDER, POOL, 1000),
+            transfer(MAINNET.weth, POOL, SENDER, 7),
+            // Unrelated transfers of the same tokens.
+            transfer(usdc, POOL, ROUTER, 5),
+            transfer(MAINNET.weth, POOL, ROUTER, 3),
+        ];
+        let amounts = settled_amounts(&logs, MAINNET.weth, SENDER, Some(usdc), Some(MAINNET.weth));
+        assert_eq!(amounts, (U256::from(1000), U256::from(7)));
+    }
+
+    #[test]
+    fn test_settled_amounts_for_native_legs() {
+        let usdc = MAINNET.usd_token;
+        let deposit = WETH9::Deposit { dst: ROUTER, wad: U256::from(100) };
+        let refund = WETH9::Withdrawal { src: ROUTER, wad: U256::from(40) };
+        let logs = vec![
+            log(MAINNET.weth, deposit.encode_log_data()),
+            transfer(usdc, POOL, SENDER, 2000),
+            log(MAINNET.weth, refund.encode_log_data()),
+        ];
+        let amounts = settled_amounts(&logs, MAINNET.weth, SENDER, None, Some(usdc));
+        assert_eq!(amounts, (U256::from(60), U256::from(2000)));
+
+        let payout = WETH9::Withdrawal { src: ROUTER, wad: U256::from(9) };
+        let logs = vec![
+            transfer(usdc, SENDER, POOL, 2000),
+            log(MAINNET.weth, payout.encode_log_data()),
+        ];
+        let amounts = settled_amounts(&logs, MAINNET.weth, SENDER, Some(usdc), None);
+        assert_eq!(amounts, (U256::from(2000), U256::from(9)));
+    }
+
+    #[test]
+    fn test_swap_summary_lists_amounts_gas_and_approvals() {
+        let simulation: SwapResponse = serde_json::from_value(serde_json::json!({
+            "mode": "exact_input",
+            "amount_from": "1000",
+            "amount_to": "0.3",
+            "amount_out_minimum": "0.297",
+            "mid_price": "0.0003",
+            "execution_price": "0.0003",
+            "price_impact_percent": "0.05",
+            "fee_tiers": [500],
+            "fee_percent": "0.05",
+            "gas_estimate": 150000,
+            "gas_cost": {
+                "base_fee_gwei": "10",
+                "priority_fee_gwei": "1",
+                "cost_native": "0.00165",
+            },
+            "route": [],
+            "venue": "uniswap_v3",
+            "venue_quotes": [],
+            "router": "universal_router",
+            "router_address": MAINNET.universal_router,
+            "calldata": "0x",
+            "value": "0x0",
+        }))
+        .unwrap();
+        let spend = Spend {
+            chain_id: MAINNET.chain_id,
+            token_in: PolicyToken {
+                address: Some(MAINNET.usd_token),
+                symbol: Some("USDC".to_string()),
+            },
+            amount_in: Decimal::from(1000),
+            token_out: PolicyToken {
+                address: None,
+                symbol: Some("ETH".to_string()),
+            },
+            router: MAINNET.universal_router,
+            counterparties: vec![],
+            slippage_percent: Decimal::ONE,
+            price_impact_percent: simulation.price_impact_percent,
+        };
+        let approval = Approval {
+            kind: TransactionKind::Approve,
+            to: MAINNET.usd_token,
+            data: Default::default(),
+        };
+
+        let permit = permit_single(
+            MAINNET.usd_token,
+            U256::from(1000_000000),
+            1_700_000_000,
+            U48::ZERO,
+            MAINNET.universal_router,
+            U256::from(1_700_000_000),
+        );
+        let summary = swap_summary(&MAINNET, SENDER, &spend, &simulation, Some(&permit), &[approval]);
+        assert!(summary.contains(&format!("Pay: 1000 USDC ({})", MAINNET.usd_token)));
+        assert!(summary.contains("Receive: about 0.3 ETH, at least 0.297"));
+        assert!(summary.contains(&format!("Recipient: {}", SENDER)));
+        assert!(summary.contains("Estimated gas cost: 0.00165 ETH"));
+        assert!(!summary.contains("L1 data fee"));
+        assert!(summary.contains(&format!(
+            "Signed first: Permit2 permit letting {} spend up to 1000 USDC ({}) until 2023-11-14 22:13 UTC",
+            MAINNET.universal_router, MAINNET.usd_token
+        )));
+        assert!(summary.contains(&format!("Sent first: approval transaction to {}", MAINNET.usd_token)));
+    }
+
+    fn eth_to_usdc() -> ExecuteSwapRequest {
+        ExecuteSwapRequest {
+            from_token: "ETH".to_string(),
+            to_token: "USDC".to_string(),
+            amount_from: Some("0.1".to_string()),
+            amount_to: None,
+            mode: None,
+            slippage_percent: "1".to_string(),
+            chain: Some(LOCAL.name.to_string()),
+            router: None,
+            approve: None,
+            account: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_swap_requires_allow_broadcast() {
+        let confirm = |_| async { Ok(()) };
+        let (providers, accounts) = (Providers::default(), Accounts::default());
+        let err = execute_swap(&providers, &accounts, false, &Policy::default(), confirm, eth_to_usdc())
+            .await
+            .expect_err("broadcasting should be disabled");
+        assert!(err.to_string().contains("--allow-broadcast"));
+    }
+
+    /// End-to-end swap against a mainnet fork, e.g. `anvil --fork-url $ETH_RPC_URL`; run with
+    /// `cargo test -- --ignored`. Signs with anvil's first default account.
+    #[tokio::test]
+    #[ignore = "needs an anvil mainnet fork at LOCAL_RPC_URL"]
+    async fn execute_swap_on_anvil_fork() {
+        let key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcaf784d7bf4f2ff80";
+        let providers = Providers::connect().await.unwrap();
+        let accounts = Accounts::load_with(None, Some(key.to_string())).unwrap();
+
+        let policy = Policy::default();
+        let confirm = |_| async { Ok(()) };
+        let bought = execute_swap(&providers, &accounts, true, &policy, confirm, eth_to_usdc()).await.unwrap();
+        assert!(bought.amount_to >= bought.simulation.amount_out_minimum.unwrap());
+        assert_eq!(bought.amount_from, Decimal::new(1, 1));
+
+        // Selling the USDC back needs an approval of Permit2 first.
+        let sell = ExecuteSwapRequest {
+            from_token: "USDC".to_string(),
+            to_token: "ETH".to_string(),
+            amount_from: Some(bought.amount_to.to_string()),
+            ..eth_to_usdc()
+        };
+        let sold = execute_swap(&providers, &accounts, true, &policy, confirm, sell).await.unwrap();
+        assert!(sold.approval_transaction_hash.is_some());
+        assert_eq!(sold.amount_from, bought.amount_to);
+        assert!(sold.amount_to >= sold.simulation.amount_out_minimum.unwrap());
+    }
+}
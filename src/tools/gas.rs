@@ -0,0 +1,217 @@
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::network::Ethereum;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+
+use crate::utils::chains::{ChainProfile, resolve_chain};
+use crate::utils::decimals::u256_to_decimal;
+use crate::utils::gas::{
+    FeeEstimate, NATIVE_DECIMALS, PRIORITY_PERCENTILES, fee_estimate, fee_history, wei_to_gwei,
+};
+use crate::utils::provider::Providers;
+use crate::utils::routing::find_best_route_exact_input;
+use crate::utils::token_registry::token_metadata;
+
+/// Typical gas used by common operations, for cost estimates.
+const COMMON_OPERATIONS: [(&str, u64); 3] = [
+    ("eth_transfer", 21_000),
+    ("erc20_transfer", 65_000),
+    ("uniswap_v3_swap", 184_000),
+];
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GasPriceRequest {
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PriorityFees {
+    // Serialize as string to avoid precision loss.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub slow: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub standard: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fast: Decimal,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct OperationCost {
+    /// Operation name (e.g., 'eth_transfer').
+    pub operation: String,
+    pub gas: u64,
+    /// Cost at the next base fee plus the standard priority fee, in the native asset.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub cost_native: Decimal,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub cost_usd: Option<Decimal>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct GasPriceResponse {
+    pub chain: String,
+    /// Base fee of the latest block, in gwei.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub base_fee_gwei: Decimal,
+    /// Base fee predicted for the next block, in gwei.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub next_base_fee_gwei: Decimal,
+    /// Median tips paid at the 10th, 50th and 90th percentile over recent blocks, in gwei.
+    pub priority_fee_gwei: PriorityFees,
+    /// Price of the native asset in USD; absent when it cannot be priced on this chain.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub native_price_usd: Option<Decimal>,
+    pub operations: Vec<OperationCost>,
+    /// Set on rollups whose L1 data fee, often most of a transaction's cost, is not included in
+    /// the operation costs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub excludes_l1_data_fee: bool,
+}
+
+pub async fn get_gas_price(
+    providers: &Providers,
+    chain: Option<String>,
+) -> Result<GasPriceResponse> {
+    let chain = resolve_chain(chain.as_deref())?;
+    tracing::trace!("Using provider for chain: {}", chain.name);
+    let provider = providers.get(chain)?;
+
+    let (history, native_price_usd) = tokio::join!(
+        fee_history(provider, BlockNumberOrTag::Latest, &PRIORITY_PERCENTILES),
+        native_price_usd(chain, provider),
+    );
+    let history = history?;
+    let native_price_usd = native_price_usd
+        .inspect_err(|e| tracing::warn!("Failed to price {} in USD: {}", chain.native_symbol, e))
+        .ok();
+
+    // Indices follow PRIORITY_PERCENTILES.
+    let slow = fee_estimate(&history, 0)?;
+    let standard = fee_estimate(&history, 1)?;
+    let fast = fee_estimate(&history, 2)?;
+    let base_fee = history
+        .latest_block_base_fee()
+        .context("Fee history has no base fee; the chain may not support EIP-1559")?;
+    tracing::debug!(
+        "Gas fees on {}: base fee {}, standard {:?}",
+        chain.name,
+        base_fee,
+        standard
+    );
+
+    let operations = COMMON_OPERATIONS
+        .iter()
+        .map(|&(operation, gas)| operation_cost(operation, gas, standard, native_price_usd))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(GasPriceResponse {
+        chain: chain.name.to_string(),
+        base_fee_gwei: wei_to_gwei(base_fee)?,
+        next_base_fee_gwei: wei_to_gwei(standard.base_fee_per_gas)?,
+        priority_fee_gwei: PriorityFees {
+            slow: wei_to_gwei(slow.priority_fee_per_gas)?,
+            standard: wei_to_gwei(standard.priority_fee_per_gas)?,
+            fast: wei_to_gwei(fast.priority_fee_per_gas)?,
+        },
+        native_price_usd,
+        operations,
+        excludes_l1_data_fee: chain.l1_data_fee,
+    })
+}
+
+/// Price of one unit of the native asset in the chain's USD stablecoin.
+async fn native_price_usd(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+) -> Result<Decimal> {
+    let one_native = U256::from(10).pow(U256::from(NATIVE_DECIMALS));
+    let (search, usd_token) = tokio::try_join!(
+        find_best_route_exact_input(
+            chain,
+            provider,
+            BlockId::latest(),
+            chain.weth,
+            chain.usd_token,
+            one_native,
+        ),
+        token_metadata(provider, chain.chain_id, chain.usd_token),
+    )?;
+    u256_to_decimal(search.best.amount, usd_token.decimals)
+}
+
+fn operation_cost(
+    operation: &str,
+    gas: u64,
+    fees: FeeEstimate,
+    native_price_usd: Option<Decimal>,
+) -> Result<OperationCost> {
+    let cost_native = u256_to_decimal(fees.cost(gas), NATIVE_DECIMALS)?;
+    Ok(OperationCost {
+        operation: operation.to_string(),
+        gas,
+        cost_native,
+        cost_usd: native_price_usd.map(|price| (cost_native * price).round_dp(6).normalize()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn operation_cost_prices_gas_in_native_and_usd() {
+        let fees = FeeEstimate {
+            base_fee_per_gas: 9_000_000_000,
+            priority_fee_per_gas: 1_000_000_000,
+        };
+        let cost = operation_cost("eth_transfer", 21_000, fees, Some(Decimal::from(3000))).unwrap();
+        assert_eq!(cost.cost_native, Decimal::from_str("0.00021").unwrap());
+        assert_eq!(cost.cost_usd, Some(Decimal::from_str("0.63").unwrap()));
+
+        let cost = operation_cost("eth_transfer", 21_000, fees, None).unwrap();
+        assert_eq!(cost.cost_usd, None);
+    }
+
+    #[test]
+    fn gas_price_response_serde_uses_string_fields() {
+        let response = GasPriceResponse {
+            chain: "ethereum".to_string(),
+            base_fee_gwei: Decimal::from_str("8.5").unwrap(),
+            next_base_fee_gwei: Decimal::from_str("8.9").unwrap(),
+            priority_fee_gwei: PriorityFees {
+                slow: Decimal::from_str("0.01").unwrap(),
+                standard: Decimal::from_str("0.05").unwrap(),
+                fast: Decimal::from_str("1.2").unwrap(),
+            },
+            native_price_usd: None,
+            operations: vec![],
+            excludes_l1_data_fee: false,
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("\"next_base_fee_gwei\":\"8.9\""));
+        assert!(json.contains("\"fast\":\"1.2\""));
+        assert!(!json.contains("native_price_usd"));
+        assert!(!json.contains("excludes_l1_data_fee"));
+    }
+
+    #[tokio::test]
+    async fn get_gas_price_unconfigured_chain_returns_error() {
+        let result = get_gas_price(&Providers::default(), Some("arbitrum".to_string())).await;
+        let err = result.expect_err("unconfigured chain should fail");
+        assert!(err.to_string().contains("not configured"));
+    }
+}
@@ -1,4 +1,11 @@
 pub mod balance;
+pub mod confirm;
+pub mod ens;
+pub mod execute;
+pub mod gas;
+pub mod portfolio;
 pub mod price;
 pub mod router;
 pub mod swap;
+pub mod token;
+pub mod transaction;
@@ -0,0 +1,332 @@
+use alloy::eips::BlockId;
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use rust_decimal::Decimal;
+
+use crate::utils::chains::{ChainProfile, MULTICALL3, resolve_chain};
+use crate::utils::contracts::{IERC20, Multicall3};
+use crate::utils::decimals::u256_to_decimal;
+use crate::utils::ens::{resolve_address, resolve_token_or_name};
+use crate::utils::gas::NATIVE_DECIMALS;
+use crate::utils::multicall::{Call, aggregate, decode};
+use crate::utils::provider::Providers;
+use crate::utils::routing::find_best_route_exact_input;
+use crate::utils::token_registry::{listed_tokens, token_metadata};
+
+/// Token argument selecting every token listed for the chain.
+const ALL_TOKENS: &str = "all";
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PortfolioRequest {
+    #[schemars(description = "Wallet address (e.g., '0x...') or ENS name (e.g., 'vitalik.eth')")]
+    pub wallet_address: String,
+    #[schemars(
+        description = "Token symbols (e.g., 'UNI'), addresses or the native symbol (e.g., 'ETH') to check; omit or pass ['all'] to check the native asset and every token listed for the chain"
+    )]
+    pub tokens: Option<Vec<String>>,
+    #[schemars(
+        description = "Currency symbol (e.g., 'USDC', 'WETH') or address (e.g., '0x...') to value holdings in; defaults to the chain's USDC"
+    )]
+    pub quote_currency: Option<String>,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Holding {
+    pub symbol: String,
+    /// Token address; absent for the native asset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    // Serialize as string to avoid precision loss.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub balance: Decimal,
+    /// Amount of quote currency the whole balance sells for on the best route; absent when it
+    /// cannot be priced.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub value: Option<Decimal>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct PortfolioResponse {
+    pub chain: String,
+    pub wallet_address: Address,
+    /// Symbol of the currency holdings are valued in.
+    pub quote_currency: String,
+    /// Non-zero holdings, most valuable first; holdings without a value come last.
+    pub holdings: Vec<Holding>,
+    /// Sum of every holding's value; holdings that cannot be priced are left out.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub total_value: Decimal,
+}
+
+/// Balances to check: the native asset and the given tokens.
+#[derive(Debug, PartialEq, Eq)]
+struct TokenSelection {
+    native: bool,
+    tokens: Vec<Address>,
+}
+
+pub async fn get_portfolio(
+    providers: &Providers,
+    wallet_address: String,
+    tokens: Option<Vec<String>>,
+    quote_currency: Option<String>,
+    chain: Option<String>,
+) -> Result<PortfolioResponse> {
+    let chain = resolve_chain(chain.as_deref())?;
+
+    tracing::trace!("Parsing wallet address: {}", wallet_address);
+    let wallet_address = resolve_address(providers, chain, &wallet_address)
+        .await
+        .context(format!("Invalid wallet address: {}", wallet_address))?;
+
+    tracing::trace!("Using provider for chain: {}", chain.name);
+    let provider = providers.get(chain)?;
+
+    let quote = match quote_currency.as_deref() {
+        Some(currency) if chain.is_native(currency) => chain.weth,
+        Some(currency) => resolve_token_or_name(providers, chain, currency).await?,
+        None => chain.usd_token,
+    };
+    let quote = token_metadata(provider, chain.chain_id, quote).await?;
+    tracing::trace!("Valuing holdings in {} ({})", quote.symbol, quote.address);
+
+    let selection = select_tokens(providers, chain, tokens).await?;
+    tracing::debug!("Fetching {} token balances for {}", selection.tokens.len(), wallet_address);
+    let (native_balance, balances) =
+        fetch_balances(provider, wallet_address, &selection.tokens).await?;
+
+    let mut held = Vec::new();
+    if selection.native && !native_balance.is_zero() {
+        held.push((None, native_balance));
+    }
+    for (&token, balance) in selection.tokens.iter().zip(balances) {
+        match balance {
+            Some(balance) if !balance.is_zero() => held.push((Some(token), balance)),
+            Some(_) => {}
+            None => tracing::debug!("balanceOf failed for token {}; skipping it", token),
+        }
+    }
+    tracing::debug!("{} non-zero holdings for {}", held.len(), wallet_address);
+
+    let holdings = join_all(held.into_iter().map(|(token, balance)| {
+        holding(chain, provider, token, balance, quote.address, quote.decimals)
+    }))
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    let (holdings, total_value) = rank_holdings(holdings);
+    Ok(PortfolioResponse {
+        chain: chain.name.to_string(),
+        wallet_address,
+        quote_currency: quote.symbol,
+        holdings,
+        total_value,
+    })
+}
+
+/// Resolve the requested tokens; none, or 'all', selects the native asset and every listed token.
+async fn select_tokens(
+    providers: &Providers,
+    chain: &'static ChainProfile,
+    tokens: Option<Vec<String>>,
+) -> Result<TokenSelection> {
+    let tokens = tokens.unwrap_or_default();
+    if tokens.is_empty() || tokens.iter().any(|token| token.trim().eq_ignore_ascii_case(ALL_TOKENS)) {
+        let listed = listed_tokens(chain.chain_id).await?;
+        return Ok(TokenSelection {
+            native: true,
+            tokens: listed.into_iter().map(|token| token.address).collect(),
+        });
+    }
+
+    let mut selection = TokenSelection {
+        native: false,
+        tokens: Vec::new(),
+    };
+    for token in &tokens {
+        if chain.is_native(token) {
+            selection.native = true;
+            continue;
+        }
+        let address = resolve_token_or_name(providers, chain, token).await?;
+        if !selection.tokens.contains(&address) {
+            selection.tokens.push(address);
+        }
+    }
+    Ok(selection)
+}
+
+/// Native balance and every token's `balanceOf` in one batch of calls.
+/// A token whose `balanceOf` reverts gets `None`.
+async fn fetch_balances(
+    provider: &impl Provider<Ethereum>,
+    wallet_address: Address,
+    tokens: &[Address],
+) -> Result<(U256, Vec<Option<U256>>)> {
+    let native_call = Call::new(MULTICALL3, &Multicall3::getEthBalanceCall { addr: wallet_address });
+    let balance_call = IERC20::balanceOfCall { account: wallet_address };
+    let calls: Vec<Call> = std::iter::once(native_call)
+        .chain(tokens.iter().map(|&token| Call::new(token, &balance_call)))
+        .collect();
+
+    let block = BlockId::latest();
+    let results = aggregate(provider, block, &calls)
+        .await
+        .context("Failed to fetch balances")?;
+    let (native, tokens) = results.split_first().context("No balance results")?;
+
+    // Without Multicall3 its getEthBalance has nothing to call, so ask the node directly.
+    let native_balance = match decode::<Multicall3::getEthBalanceCall>(native.as_ref()) {
+        Some(balance) => balance,
+        None => provider
+            .get_balance(wallet_address)
+            .block_id(block)
+            .await
+            .context("Failed to fetch native balance")?,
+    };
+    let balances = tokens
+        .iter()
+        .map(|output| decode::<IERC20::balanceOfCall>(output.as_ref()))
+        .collect();
+    Ok((native_balance, balances))
+}
+
+/// Format one holding and value it by quoting its whole balance into the quote currency.
+async fn holding(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    token: Option<Address>,
+    balance: U256,
+    quote: Address,
+    quote_decimals: u8,
+) -> Result<Holding> {
+    let (symbol, decimals) = match token {
+        Some(token) => {
+            let metadata = token_metadata(provider, chain.chain_id, token).await?;
+            (metadata.symbol, metadata.decimals)
+        }
+        None => (chain.native_symbol.to_string(), NATIVE_DECIMALS),
+    };
+
+    // The native asset is priced as its wrapped token.
+    let priced_token = token.unwrap_or(chain.weth);
+    let value = if priced_token == quote {
+        Some(u256_to_decimal(balance, quote_decimals)?)
+    } else {
+        let block = BlockId::latest();
+        match find_best_route_exact_input(chain, provider, block, priced_token, quote, balance).await {
+            Ok(search) => Some(u256_to_decimal(search.best.amount, quote_decimals)?),
+            Err(e) => {
+                tracing::warn!("Failed to value {} {}: {}", balance, symbol, e);
+                None
+            }
+        }
+    };
+
+    Ok(Holding {
+        symbol,
+        address: token,
+        balance: u256_to_decimal(balance, decimals)?,
+        value,
+    })
+}
+
+/// Sort holdings by value, most valuable first, and sum the values.
+fn rank_holdings(mut holdings: Vec<Holding>) -> (Vec<Holding>, Decimal) {
+    holdings.sort_by_key(|holding| std::cmp::Reverse(holding.value));
+    let total_value = holdings.iter().filter_map(|holding| holding.value).sum();
+    (holdings, total_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::MAINNET;
+    use std::str::FromStr;
+
+    fn holding(symbol: &str, value: Option<&str>) -> Holding {
+        Holding {
+            symbol: symbol.to_string(),
+            address: None,
+            balance: Decimal::ONE,
+            value: value.map(|value| Decimal::from_str(value).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_rank_holdings_sorts_by_value_and_sums() {
+        let holdings = vec![
+            holding("UNI", Some("12.5")),
+            holding("SCAM", None),
+            holding("ETH", Some("3000")),
+        ];
+        let (holdings, total) = rank_holdings(holdings);
+        let symbols: Vec<&str> = holdings.iter().map(|holding| holding.symbol.as_str()).collect();
+        assert_eq!(symbols, ["ETH", "UNI", "SCAM"]);
+        assert_eq!(total, Decimal::from_str("3012.5").unwrap());
+    }
+
+    #[test]
+    fn portfolio_response_serde_uses_string_fields() {
+        let response = PortfolioResponse {
+            chain: "ethereum".to_string(),
+            wallet_address: Address::ZERO,
+            quote_currency: "USDC".to_string(),
+            holdings: vec![holding("ETH", Some("3000.25")), holding("SCAM", None)],
+            total_value: Decimal::from_str("3000.25").unwrap(),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["total_value"], "3000.25");
+        assert_eq!(json["holdings"][0]["value"], "3000.25");
+        assert_eq!(json["holdings"][0]["balance"], "1");
+        assert!(json["holdings"][1].get("value").is_none());
+        assert!(json["holdings"][1].get("address").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_select_tokens() {
+        let providers = Providers::default();
+        let all = select_tokens(&providers, &MAINNET, None).await.unwrap();
+        assert!(all.native);
+        assert!(all.tokens.contains(&MAINNET.usd_token));
+
+        let tokens = vec!["all".to_string(), "USDC".to_string()];
+        assert_eq!(select_tokens(&providers, &MAINNET, Some(tokens)).await.unwrap(), all);
+
+        let tokens = vec!["eth".to_string(), "USDC".to_string(), MAINNET.usd_token.to_string()];
+        let selection = select_tokens(&providers, &MAINNET, Some(tokens)).await.unwrap();
+        assert_eq!(
+            selection,
+            TokenSelection {
+                native: true,
+                tokens: vec![MAINNET.usd_token],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_portfolio_unconfigured_chain_returns_error() {
+        let result = get_portfolio(
+            &Providers::default(),
+            "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".to_string(),
+            None,
+            None,
+            Some("base".to_string()),
+        )
+        .await;
+        let err = result.expect_err("unconfigured chain should fail");
+        assert!(err.to_string().contains("not configured"));
+    }
+}
@@ -1,17 +1,15 @@
-#![allow(dead_code)]
-
-use alloy::primitives::{Address, U256, Uint, address};
-use anyhow::{Context, Result, bail};
+use alloy::primitives::U256;
+use anyhow::Result;
 use rust_decimal::Decimal;
 
-use crate::utils::contracts::{IERC20, UniswapV3Quoter};
+use crate::utils::block::{PinnedBlock, block_id, pin_block};
+use crate::utils::chains::resolve_chain;
 use crate::utils::decimals::u256_to_decimal;
-use crate::utils::provider::make_provider;
-use crate::utils::token_registry::resolve_token;
-
-pub const UNISWAP_V3_QUOTER_ADDRESS: Address =
-    address!("0xb27308f9F90D607463bb33ea1BeBb41C27CE5AB6");
-pub const FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+use crate::utils::mid_price::{price_impact_percent, route_fee_percent, route_mid_price};
+use crate::utils::provider::Providers;
+use crate::utils::routing::{RouteHop, find_best_route_exact_input};
+use crate::utils::ens::resolve_token_or_name;
+use crate::utils::token_registry::token_metadata;
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct PriceRequest {
@@ -21,6 +19,23 @@ pub struct PriceRequest {
         description = "Currency symbol (e.g., 'USDC', 'USDT', 'WETH') or address (e.g., '0x...')"
     )]
     pub currency: String,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+    #[schemars(
+        description = "Block to price at: number (e.g., '19000000'), hash, tag (e.g., 'finalized') or ISO 8601 timestamp (e.g., '2024-01-01T00:00:00Z'); defaults to latest. Past blocks need an archive node"
+    )]
+    pub block: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct VenueQuote {
+    /// Venue name (e.g., 'uniswap_v3', 'uniswap_v2', 'sushiswap').
+    pub venue: String,
+    // Serialize as string to avoid precision loss.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub amount: Decimal,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -28,25 +43,51 @@ pub struct PriceResponse {
     // Serialize as string to avoid precision loss.
     #[serde(with = "rust_decimal::serde::str")]
     pub price: Decimal,
+    /// Mid price of the route before fees and price impact.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub mid_price: Decimal,
+    /// Shortfall of price (quoted for 1 token) against mid_price after fee_percent, in percent.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price_impact_percent: Decimal,
+    /// Route used for the quote, one entry per pool hop.
+    pub route: Vec<RouteHop>,
+    /// Fee tier of each hop of the route, in hundredths of a bip (e.g., 3000 = 0.3%).
+    pub fee_tiers: Vec<u32>,
+    /// Total fee charged along the route, in percent.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fee_percent: Decimal,
+    /// Venue that gave the best price.
+    pub venue: String,
+    /// Best price found on each venue.
+    pub venue_quotes: Vec<VenueQuote>,
+    /// Block the price was quoted at; absent when quoting the latest state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<PinnedBlock>,
 }
 
-pub async fn get_token_price(token: String, currency: String) -> Result<PriceResponse> {
-    let provider = make_provider()?;
+pub async fn get_token_price(
+    providers: &Providers,
+    token: String,
+    currency: String,
+    chain: Option<String>,
+    block: Option<String>,
+) -> Result<PriceResponse> {
+    let chain = resolve_chain(chain.as_deref())?;
+    let provider = providers.get(chain)?;
+    let block = pin_block(provider, block.as_deref()).await?;
+    let block_id = block_id(block.as_ref());
 
     tracing::debug!("Resolving token: {} and currency: {}", token, currency);
-    let token_addr = resolve_token(&token).await?;
-    let currency_addr = resolve_token(&currency).await?;
+    let token_addr = resolve_token_or_name(providers, chain, &token).await?;
+    let currency_addr = resolve_token_or_name(providers, chain, &currency).await?;
     tracing::trace!("Token address: {}, Currency address: {}", token_addr, currency_addr);
 
-    let token_contract = IERC20::new(token_addr, &provider);
-    let currency_contract = IERC20::new(currency_addr, &provider);
-
     tracing::trace!("Fetching decimals for token and currency");
-    let (token_decimals, currency_decimals) =
-        tokio::try_join!(async { token_contract.decimals().call().await }, async {
-            currency_contract.decimals().call().await
-        },)
-        .context("Failed to fetch token/currency decimals")?;
+    let (token_metadata, currency_metadata) = tokio::try_join!(
+        token_metadata(provider, chain.chain_id, token_addr),
+        token_metadata(provider, chain.chain_id, currency_addr),
+    )?;
+    let (token_decimals, currency_decimals) = (token_metadata.decimals, currency_metadata.decimals);
     tracing::trace!("Token decimals: {}, Currency decimals: {}", token_decimals, currency_decimals);
 
     // IMPORTANT: Set the input amount to 1 token (10^token_decimals).
@@ -54,52 +95,53 @@ pub async fn get_token_price(token: String, currency: String) -> Result<PriceRes
     let amount_in_u256 = U256::from(10).pow(U256::from(token_decimals));
     tracing::trace!("Query amount: {} (1 token)", amount_in_u256);
 
-    let quoter = UniswapV3Quoter::new(UNISWAP_V3_QUOTER_ADDRESS, &provider);
-
-    // Try all fee tiers and find the best price.
-    let mut best_out = U256::ZERO;
-    let mut best_fee = None;
-
-    tracing::debug!("Querying Uniswap V3 quoter for {}/{}", token, currency);
-    for &fee in &FEE_TIERS {
-        let fee_uint = Uint::<24, 1>::from_limbs([fee.into()]);
-
-        let result = quoter
-            .quoteExactInputSingle(
-                token_addr,
-                currency_addr,
-                fee_uint,
-                amount_in_u256,
-                Uint::ZERO, // sqrtPriceLimitX96 = 0
-            )
-            .call()
-            .await;
-
-        if let Ok(quote) = result {
-            let amount_out = quote;
-            tracing::trace!("Fee tier {}: quote = {}", fee, amount_out);
-            if amount_out > best_out {
-                best_out = amount_out;
-                best_fee = Some(fee);
-            }
-        } else {
-            tracing::trace!("Fee tier {}: no liquidity or error", fee);
-        }
-    }
-
-    if best_out == U256::ZERO {
-        tracing::warn!("No liquidity found for pair {}/{} in any V3 pool", token, currency);
-        bail!(
-            "No liquidity found for pair {}/{} in V3 pools",
-            token,
-            currency
-        );
-    }
-
-    tracing::debug!("Best fee tier: {:?}, best quote: {}", best_fee, best_out);
+    // Try direct and multi-hop routes on V3 and V2 venues and find the best price.
+    tracing::debug!("Querying V3 and V2 quotes for {}/{}", token, currency);
+    let search = find_best_route_exact_input(
+        chain,
+        provider,
+        block_id,
+        token_addr,
+        currency_addr,
+        amount_in_u256,
+    )
+    .await?;
+    let route = &search.best.route;
+
+    tracing::debug!(
+        "Best venue: {}, best route: {:?}, best quote: {}",
+        route.venue.name(),
+        route.hops(),
+        search.best.amount
+    );
+
+    let mid_price =
+        route_mid_price(chain, provider, block_id, route, token_decimals, currency_decimals).await?;
+    let price = u256_to_decimal(search.best.amount, currency_decimals)?;
+    let fee_tiers: Vec<u32> = route.hops().iter().map(|hop| hop.fee).collect();
+    let fee_percent = route_fee_percent(&fee_tiers);
+
+    let venue_quotes = search
+        .venues
+        .iter()
+        .map(|q| {
+            Ok(VenueQuote {
+                venue: q.route.venue.name().to_string(),
+                amount: u256_to_decimal(q.amount, currency_decimals)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     Ok(PriceResponse {
-        price: u256_to_decimal(best_out, currency_decimals)?,
+        price,
+        mid_price,
+        price_impact_percent: price_impact_percent(mid_price, price, fee_percent),
+        route: route.hops(),
+        fee_percent,
+        fee_tiers,
+        venue: route.venue.name().to_string(),
+        venue_quotes,
+        block,
     })
 }
 
@@ -113,9 +155,21 @@ mod tests {
     #[test]
     fn price_response_serde_uses_string_field() {
         let decimal = Decimal::from_str("1.2345").unwrap();
-        let response = PriceResponse { price: decimal };
+        let response = PriceResponse {
+            price: decimal,
+            mid_price: Decimal::from_str("1.25").unwrap(),
+            price_impact_percent: Decimal::from_str("1.24").unwrap(),
+            route: vec![],
+            fee_tiers: vec![500],
+            fee_percent: Decimal::from_str("0.05").unwrap(),
+            venue: "uniswap_v3".to_string(),
+            venue_quotes: vec![],
+            block: None,
+        };
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"price\":\"1.2345\""));
+        assert!(json.contains("\"mid_price\":\"1.25\""));
+        assert!(json.contains("\"price_impact_percent\":\"1.24\""));
 
         let parsed: PriceResponse = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.price, response.price);
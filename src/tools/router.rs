@@ -1,30 +1,60 @@
 use crate::tools::{
     balance::{BalanceRequest, get_balance},
+    ens::{ReverseResolveRequest, reverse_resolve_address},
+    execute::{ExecuteSwapRequest, execute_swap},
+    gas::{GasPriceRequest, get_gas_price},
+    portfolio::{PortfolioRequest, get_portfolio},
     price::{PriceRequest, get_token_price},
     swap::{SwapRequest, swap_tokens},
+    token::{SearchTokensRequest, TokenInfoRequest, get_token_info, search_tokens},
 };
 use rmcp::{
     ServerHandler,
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::{CallToolResult, ErrorData, ServerCapabilities, ServerInfo},
+    service::{Peer, RoleServer},
     tool, tool_handler, tool_router,
 };
+use crate::tools::confirm::confirm_with_user;
+use crate::utils::accounts::Accounts;
+use crate::utils::policy::{Policy, PolicyViolation};
+use crate::utils::provider::Providers;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct EthTools {
     tool_router: ToolRouter<Self>,
+    providers: Providers,
+    /// Named signers loaded at startup.
+    accounts: Accounts,
+    /// Whether tools may sign and broadcast transactions (`--allow-broadcast`).
+    allow_broadcast: bool,
+    /// Spending policy checked before any transaction is signed.
+    policy: Arc<Policy>,
 }
 
 #[tool_router]
 impl EthTools {
-    pub fn new() -> Self {
+    pub fn new(
+        providers: Providers,
+        accounts: Accounts,
+        allow_broadcast: bool,
+        policy: Arc<Policy>,
+    ) -> Self {
         Self {
             tool_router: Self::tool_router(),
+            providers,
+            accounts,
+            allow_broadcast,
+            policy,
         }
     }
 
     #[tool(description = "Query ETH and ERC20 token balances;\n
+    The wallet may be an address or an ENS name (e.g., 'vitalik.eth');\n
     If token address is not provided, the balance of native asset will be returned;\n
+    Optionally select the chain (e.g., 'arbitrum', 'base'); defaults to Ethereum mainnet;\n
+    Optionally pin the query to a past block by number, hash, tag or ISO 8601 timestamp (needs an archive node);\n
     Output: balance in formatted decimal format.
     ")]
     async fn get_balance(
@@ -32,10 +62,12 @@ impl EthTools {
         Parameters(BalanceRequest {
             wallet_address,
             token,
+            chain,
+            block,
         }): Parameters<BalanceRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        tracing::info!("get_balance called: wallet={}, token={:?}", wallet_address, token);
-        match get_balance(wallet_address.clone(), token.clone()).await {
+        tracing::info!("get_balance called: wallet={}, token={:?}, chain={:?}, block={:?}", wallet_address, token, chain, block);
+        match get_balance(&self.providers, wallet_address.clone(), token.clone(), chain, block).await {
             Ok(resp) => {
                 tracing::info!("get_balance succeeded: wallet={}, balance={}", wallet_address, resp.balance);
                 let value = serde_json::to_value(resp)
@@ -50,16 +82,54 @@ impl EthTools {
     }
 
     #[tool(
-        description = "Get the price of a token in the specified currency by querying Uniswap V3 Quoter.\n
-    Output: price in formatted decimal format.
+        description = "Get a wallet's balances of many tokens at once, valued in a quote currency.\n
+    The wallet may be an address or an ENS name; pass a list of tokens (symbols, addresses or the native symbol) or omit it to check every token listed for the chain;\n
+    Balances are fetched in a single Multicall3 call and zero balances are dropped; each holding is valued by quoting its whole balance on the best Uniswap V3 or V2 route;\n
+    Optionally select the quote currency (defaults to USDC) and the chain (defaults to Ethereum mainnet);\n
+    Output: holdings with symbol, address, balance and value, most valuable first, and the total value.
+    "
+    )]
+    async fn get_portfolio(
+        &self,
+        Parameters(PortfolioRequest {
+            wallet_address,
+            tokens,
+            quote_currency,
+            chain,
+        }): Parameters<PortfolioRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tracing::info!("get_portfolio called: wallet={}, tokens={:?}, quote_currency={:?}, chain={:?}", wallet_address, tokens, quote_currency, chain);
+        match get_portfolio(&self.providers, wallet_address.clone(), tokens, quote_currency, chain).await {
+            Ok(resp) => {
+                tracing::info!("get_portfolio succeeded: wallet={}, holdings={}, total_value={} {}", wallet_address, resp.holdings.len(), resp.total_value, resp.quote_currency);
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("get_portfolio failed: wallet={}, error={}", wallet_address, e);
+                Err(ErrorData::internal_error(e.to_string(), None))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Get the price of a token in the specified currency by querying Uniswap V3 and V2 venues.\n
+    Optionally price at a past block by number, hash, tag or ISO 8601 timestamp (needs an archive node).\n
+    Output: price in formatted decimal format, mid_price and price_impact_percent (market impact beyond the pool fees in fee_percent), the venue, route and fee tiers used, and the best price on each venue.
     "
     )]
     async fn get_token_price(
         &self,
-        Parameters(PriceRequest { token, currency }): Parameters<PriceRequest>,
+        Parameters(PriceRequest {
+            token,
+            currency,
+            chain,
+            block,
+        }): Parameters<PriceRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        tracing::info!("get_token_price called: token={}, currency={}", token, currency);
-        match get_token_price(token.clone(), currency.clone()).await {
+        tracing::info!("get_token_price called: token={}, currency={}, chain={:?}, block={:?}", token, currency, chain, block);
+        match get_token_price(&self.providers, token.clone(), currency.clone(), chain, block).await {
             Ok(resp) => {
                 tracing::info!("get_token_price succeeded: token={}, currency={}, price={}", token, currency, resp.price);
                 let value = serde_json::to_value(resp)
@@ -74,37 +144,187 @@ impl EthTools {
     }
 
     #[tool(
-        description = "Simulate a Uniswap V3 token swap to estimate output amount and gas cost.\n
+        description = "Search the loaded token lists by symbol or name, tolerating partial matches and typos.\n
+    Use it to find the right symbol or address before calling other tools; optionally restrict to one chain.\n
+    Output: matching tokens, best first, with chain, address, symbol, name, decimals and the token list they come from.
+    "
+    )]
+    async fn search_tokens(
+        &self,
+        Parameters(SearchTokensRequest { query, chain, limit }): Parameters<SearchTokensRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tracing::info!("search_tokens called: query={}, chain={:?}, limit={:?}", query, chain, limit);
+        match search_tokens(query.clone(), chain, limit).await {
+            Ok(resp) => {
+                tracing::info!("search_tokens succeeded: query={}, results={}", query, resp.results.len());
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("search_tokens failed: query={}, error={}", query, e);
+                Err(ErrorData::internal_error(e.to_string(), None))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Read a token's metadata on chain for an arbitrary address (or listed symbol).\n
+    Optionally select the chain (e.g., 'arbitrum', 'base'); defaults to Ethereum mainnet;\n
+    Output: whether contract code exists at the address, name, symbol, decimals, total supply, and the token list that lists it, if any.
+    "
+    )]
+    async fn get_token_info(
+        &self,
+        Parameters(TokenInfoRequest { token, chain }): Parameters<TokenInfoRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tracing::info!("get_token_info called: token={}, chain={:?}", token, chain);
+        match get_token_info(&self.providers, token.clone(), chain).await {
+            Ok(resp) => {
+                tracing::info!("get_token_info succeeded: token={}, address={}, has_code={}", token, resp.address, resp.has_code);
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("get_token_info failed: token={}, error={}", token, e);
+                Err(ErrorData::internal_error(e.to_string(), None))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Look up the primary ENS name of an address (reverse resolution).\n
+    The name is only returned if it resolves back to the same address; ENS names are also accepted wherever other tools take an address or token.\n
+    Output: the address and its verified ENS name, if any.
+    "
+    )]
+    async fn reverse_resolve(
+        &self,
+        Parameters(ReverseResolveRequest { address, chain }): Parameters<ReverseResolveRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tracing::info!("reverse_resolve called: address={}, chain={:?}", address, chain);
+        match reverse_resolve_address(&self.providers, address.clone(), chain).await {
+            Ok(resp) => {
+                tracing::info!("reverse_resolve succeeded: address={}, name={:?}", address, resp.name);
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("reverse_resolve failed: address={}, error={}", address, e);
+                Err(ErrorData::internal_error(e.to_string(), None))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Get current gas fees on a chain from recent eth_feeHistory.\n
+    Optionally select the chain (e.g., 'arbitrum', 'base'); defaults to Ethereum mainnet;\n
+    Output: latest and next-block base fee, slow/standard/fast priority fees (10th/50th/90th percentile) in gwei,
+    and the estimated cost of an ETH transfer, an ERC20 transfer and a Uniswap V3 swap in the native asset and USD.
+    On rollups (Arbitrum, Optimism, Base) costs cover L2 execution gas only, flagged by excludes_l1_data_fee.
+    "
+    )]
+    async fn get_gas_price(
+        &self,
+        Parameters(GasPriceRequest { chain }): Parameters<GasPriceRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tracing::info!("get_gas_price called: chain={:?}", chain);
+        match get_gas_price(&self.providers, chain.clone()).await {
+            Ok(resp) => {
+                tracing::info!("get_gas_price succeeded: chain={}, next_base_fee={}", resp.chain, resp.next_base_fee_gwei);
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("get_gas_price failed: chain={:?}, error={}", chain, e);
+                Err(ErrorData::internal_error(e.to_string(), None))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Simulate a token swap on the best Uniswap V3 or V2 venue to estimate output amount and gas cost.\n
+        Supports exact input (amount_from) and exact output (amount_to) swaps; use the native symbol (e.g., ETH) to swap the native asset.\n
+        Swaps go through the Uniswap Universal Router with Permit2 by default; set router to 'venue_router' to use the venue's own router.\n
         This is a simulation only - no transaction will be broadcast to the blockchain.\n
-        Output: estimated amount_out and gas_estimate.
+        Optionally simulate at a past block by number, hash, tag or ISO 8601 timestamp (needs an archive node).\n
+        Simulates from from_address, or from a configured account (the 'default' one unless account names another).\n
+        Set build_transaction to also get the unsigned transactions to sign elsewhere: any approvals from_address still needs, then the swap, each with to, data, value, nonce, EIP-1559 fees and a buffered gas limit; when a Permit2 approval is needed, permit holds the Permit2 PermitSingle as EIP-712 typed data. To skip that approval, have from_address sign the typed data and call again with signed_permit (its message and the signature): the returned swap transaction then carries the permit.\n
+        Output: amount_from, amount_to, amount_source ('simulation', or 'quote' when the RPC cannot measure a Universal Router swap), amount_out_minimum (exact input only), amount_in_maximum (exact output only), mid_price, execution_price, price_impact_percent (excluding pool fees), fee_tiers, fee_percent, gas_estimate, gas_cost (EIP-1559 fees and cost in native and to_token; excludes_l1_data_fee on rollups, where the L1 data fee is left out), net_amount_to, route, venue, venue_quotes, router, router_address, calldata, value, transactions and permit (with build_transaction).
         "
     )]
     async fn swap_tokens(
         &self,
-        Parameters(SwapRequest {
-            from_token,
-            to_token,
-            amount_from,
-            slippage_percent,
-        }): Parameters<SwapRequest>,
+        Parameters(request): Parameters<SwapRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        tracing::info!("swap_tokens called: from={}, to={}, amount={}, slippage={}%", 
-            from_token, to_token, amount_from, slippage_percent);
-        match swap_tokens(from_token.clone(), to_token.clone(), amount_from.clone(), slippage_percent.clone()).await {
+        let from_token = request.from_token.clone();
+        let to_token = request.to_token.clone();
+        tracing::info!("swap_tokens called: from={}, to={}, amount_from={:?}, amount_to={:?}, mode={:?}, slippage={}%, chain={:?}, block={:?}, account={:?}, build_transaction={:?}", 
+            from_token, to_token, request.amount_from, request.amount_to, request.mode, request.slippage_percent, request.chain, request.block, request.account, request.build_transaction);
+        match swap_tokens(&self.providers, &self.accounts, request).await {
             Ok(resp) => {
-                tracing::info!("swap_tokens succeeded: from={}, to={}, amount_out={}, gas={}", 
-                    from_token, to_token, resp.amount_to, resp.gas_estimate);
+                tracing::info!("swap_tokens succeeded: from={}, to={}, amount_from={}, amount_to={}, gas={}", 
+                    from_token, to_token, resp.amount_from, resp.amount_to, resp.gas_estimate);
                 let value = serde_json::to_value(resp)
                     .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
                 Ok(CallToolResult::structured(value))
             }
             Err(e) => {
-                tracing::error!("swap_tokens failed: from={}, to={}, amount={}, error={}", 
-                    from_token, to_token, amount_from, e);
+                tracing::error!("swap_tokens failed: from={}, to={}, error={}", 
+                    from_token, to_token, e);
                 Err(ErrorData::internal_error(e.to_string(), None))
             }
         }
     }
+
+    #[tool(
+        description = "Execute a token swap for real: sign it with the configured wallet, broadcast it and wait for the receipt.\n
+        Only available when the server runs with --allow-broadcast and a signer is configured (ETH_PRIVATE_KEY or an accounts file); otherwise the call is refused.\n
+        Signs with the configured account named by account, defaulting to the 'default' account (or the only one).\n
+        Takes the same arguments as swap_tokens (without from_address or block); the swap is simulated first and sent exactly as simulated, with its slippage limits.\n
+        If the input token's allowance is insufficient, an approval for the exact amount is sent first unless approve is false.\n
+        Before anything is signed, the client user is asked to confirm a summary (tokens, amounts, minimum out or maximum in, gas cost, recipient) through MCP elicitation; clients without elicitation support are refused.\n
+        Nothing is signed unless the swap passes the server's spending policy (token allowlist, per-transaction and daily caps, allowed routers and counterparties, maximum slippage and price impact); a denial names the rule in the error data.\n
+        Output: approval_transaction_hash, transaction_hash, block_number, gas_used, gas_cost_native, amount_from and amount_to actually settled (read from the receipt's logs), and the simulation it was built from.
+        "
+    )]
+    async fn execute_swap(
+        &self,
+        peer: Peer<RoleServer>,
+        Parameters(request): Parameters<ExecuteSwapRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let from_token = request.from_token.clone();
+        let to_token = request.to_token.clone();
+        tracing::info!("execute_swap called: from={}, to={}, amount_from={:?}, amount_to={:?}, mode={:?}, slippage={}%, chain={:?}, account={:?}",
+            from_token, to_token, request.amount_from, request.amount_to, request.mode, request.slippage_percent, request.chain, request.account);
+        let confirm = |summary| async move { confirm_with_user(&peer, summary).await };
+        let (providers, accounts) = (&self.providers, &self.accounts);
+        match execute_swap(providers, accounts, self.allow_broadcast, &self.policy, confirm, request).await {
+            Ok(resp) => {
+                tracing::info!("execute_swap succeeded: from={}, to={}, tx={}, amount_from={}, amount_to={}",
+                    from_token, to_token, resp.transaction_hash, resp.amount_from, resp.amount_to);
+                let value = serde_json::to_value(resp)
+                    .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                Ok(CallToolResult::structured(value))
+            }
+            Err(e) => {
+                tracing::error!("execute_swap failed: from={}, to={}, error={}", from_token, to_token, e);
+                Err(signing_error(e))
+            }
+        }
+    }
+}
+
+/// Error of a signing tool: policy denials become invalid requests carrying the violated rule as
+/// structured data, anything else an internal error.
+fn signing_error(e: anyhow::Error) -> ErrorData {
+    match e.downcast_ref::<PolicyViolation>() {
+        Some(violation) => ErrorData::invalid_request(e.to_string(), serde_json::to_value(violation).ok()),
+        None => ErrorData::internal_error(e.to_string(), None),
+    }
 }
 
 #[tool_handler]
@@ -1,228 +1,1182 @@
-#![allow(dead_code)]
-
+use alloy::eips::{BlockId, BlockNumberOrTag};
+use alloy::dyn_abi::TypedData;
 use alloy::hex::FromHex;
 use alloy::network::Ethereum;
-use alloy::primitives::aliases::U24;
-use alloy::primitives::{Address, Bytes, U256, Uint, address, keccak256};
+use alloy::primitives::{Address, B256, Bytes, U256, Uint, address, keccak256};
 use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
 use alloy::rpc::types::eth::state::{AccountOverride, StateOverride};
+use alloy::rpc::types::simulate::{SimBlock, SimulatePayload};
+use alloy::sol_types::{SolCall, SolValue};
 use anyhow::{Context, Result, bail};
 use rust_decimal::{Decimal, dec};
 use std::str::FromStr;
 
-use crate::tools::price::{FEE_TIERS, UNISWAP_V3_QUOTER_ADDRESS};
-use crate::utils::contracts::IV3SwapRouter::ExactInputSingleParams;
-use crate::utils::contracts::{IERC20, UniswapV3Quoter, UniswapV3Router};
+use crate::tools::price::VenueQuote;
+use crate::tools::transaction::{
+    TransactionKind, UnsignedTransaction, build_transactions, check_signed_permit, permit2_permit,
+};
+use crate::utils::block::{PinnedBlock, block_id, pin_block};
+use crate::utils::chains::{ChainProfile, MULTICALL3, PERMIT2, resolve_chain};
+use crate::utils::contracts::IV3SwapRouter::{
+    ExactInputParams, ExactInputSingleParams, ExactOutputParams, ExactOutputSingleParams,
+};
+use crate::utils::contracts::IAllowanceTransfer::PermitSingle;
+use crate::utils::contracts::{
+    IERC20, Multicall3, UniswapV2Factory, UniswapV2Router, UniswapV3Router,
+};
 use crate::utils::decimals::{decimal_to_u256, u256_to_decimal};
-use crate::utils::provider::{get_wallet_address, make_provider};
-use crate::utils::token_registry::resolve_token;
+use crate::utils::gas::{FeeEstimate, NATIVE_DECIMALS, estimate_fees, wei_to_gwei};
+use crate::utils::mid_price::{
+    execution_price, price_impact_percent, route_fee_percent, route_mid_price,
+};
+use crate::utils::accounts::Accounts;
+use crate::utils::provider::Providers;
+use crate::utils::routing::{
+    Route, RouteHop, RouteSearch, Venue, find_best_route_exact_input, find_best_route_exact_output,
+};
+use crate::utils::ens::{resolve_address, resolve_token_or_name};
+use crate::utils::token_registry::token_metadata;
+use crate::utils::universal_router::{
+    ADDRESS_THIS, MSG_SENDER, UniversalRouterCall, permit_typed_data, permit2_allowance_slot,
+    unlimited_permit2_allowance,
+};
 
-const UNISWAP_V3_ROUTER_ADDRESS: Address = address!("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45");
-const USDT_ADDRESS: Address = address!("0xdAC17F958D2ee523a2206206994597C13D831ec7");
-const USDC_ADDRESS: Address = address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
 const MOCK_BYTECODE_HEX: &str = include_str!("../../sol/MockToken.hex");
+// Simulation sender used when neither `from_address` nor a configured account is available.
+const DEFAULT_SIMULATION_ADDRESS: Address = address!("0x000000000000000000000000000000000000dEaD");
+// V2 routers require a deadline; simulated swaps use 20 minutes from now.
+const SWAP_DEADLINE_SECS: u64 = 20 * 60;
 
-#[derive(Clone, Copy)]
-struct TokenSlotConfig {
-    allowance_slot: u64,
-    balance_slot: u64,
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapMode {
+    /// Spend exactly `amount_from`, receive as much `to_token` as possible.
+    #[default]
+    ExactInput,
+    /// Receive exactly `amount_to`, spend as little `from_token` as possible.
+    ExactOutput,
 }
 
-const DEFAULT_SLOT_CONFIG: TokenSlotConfig = TokenSlotConfig {
-    allowance_slot: 1,
-    balance_slot: 0,
-};
+/// Which side of a swap, if any, is the chain's native asset. Native legs are routed through the
+/// wrapped token and (un)wrapped by the router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NativeSide {
+    Neither,
+    Input,
+    Output,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapRouter {
+    /// Uniswap Universal Router `execute` with a Permit2 approval, as used by the Uniswap front end.
+    /// Venues it cannot reach (e.g., SushiSwap) fall back to their own router.
+    #[default]
+    UniversalRouter,
+    /// The venue's own router: `SwapRouter02` for Uniswap V3, or the V2 router.
+    VenueRouter,
+}
+
+/// Where a simulated swap's amount_from and amount_to come from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountSource {
+    /// Measured in the simulated swap: the router's return values, or the sender's balance change
+    /// for the Universal Router.
+    #[default]
+    Simulation,
+    /// The route quote, when the RPC cannot simulate the balance change of a Universal Router swap.
+    Quote,
+}
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct SwapRequest {
-    #[schemars(description = "From token symbol (e.g., 'USDC') or address (e.g., '0x...')")]
+    #[schemars(
+        description = "From token symbol (e.g., 'USDC') or address (e.g., '0x...'); the native symbol (e.g., 'ETH') swaps the native asset"
+    )]
     pub from_token: String,
-    #[schemars(description = "To token symbol (e.g., 'WETH') or address (e.g., '0x...')")]
+    #[schemars(
+        description = "To token symbol (e.g., 'WETH') or address (e.g., '0x...'); the native symbol (e.g., 'ETH') receives the native asset"
+    )]
     pub to_token: String,
-    #[schemars(description = "Amount to swap from in formatted string format (e.g., '100.5')")]
+    #[schemars(
+        description = "Amount to swap from in formatted string format (e.g., '100.5'); required for exact_input"
+    )]
+    // String is used to avoid precision loss.
+    pub amount_from: Option<String>,
+    #[schemars(
+        description = "Exact amount to receive in formatted string format (e.g., '1.0'); required for exact_output"
+    )]
     // String is used to avoid precision loss.
-    pub amount_from: String,
+    pub amount_to: Option<String>,
+    #[schemars(
+        description = "Swap mode: 'exact_input' or 'exact_output'; inferred from which amount is provided if omitted"
+    )]
+    pub mode: Option<SwapMode>,
     #[schemars(description = "Slippage tolerance in percent as string format (e.g., '0.5')")]
     // String is used to avoid precision loss.
     pub slippage_percent: String,
+    #[schemars(
+        description = "Address or ENS name to simulate the swap from (e.g., '0x...', 'vitalik.eth'); defaults to the configured account, or a fixed simulation address in read-only mode"
+    )]
+    pub from_address: Option<String>,
+    #[schemars(
+        description = "Name of a configured account to simulate the swap from; defaults to the 'default' account (or the only one). Nothing is signed. Cannot be combined with from_address"
+    )]
+    pub account: Option<String>,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+    #[schemars(
+        description = "Router to simulate through: 'universal_router' (default, with Permit2) or 'venue_router'"
+    )]
+    pub router: Option<SwapRouter>,
+    #[schemars(
+        description = "Block to simulate at: number (e.g., '19000000'), hash, tag (e.g., 'finalized') or ISO 8601 timestamp (e.g., '2024-01-01T00:00:00Z'); defaults to latest. Past blocks need an archive node"
+    )]
+    pub block: Option<String>,
+    #[schemars(
+        description = "Also return the unsigned transactions to sign elsewhere (e.g., a Safe or hardware wallet): any approvals from_address still needs, then the swap, with nonces, EIP-1559 fees and buffered gas limits; defaults to false"
+    )]
+    pub build_transaction: Option<bool>,
+    #[schemars(
+        description = "With build_transaction: the permit returned by an earlier call, signed by from_address. The swap transaction then carries it and the Permit2 approval transaction is left out"
+    )]
+    pub signed_permit: Option<SignedPermit>,
+}
+
+/// A Permit2 permit from [`SwapResponse::permit`], signed by an external signer.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SignedPermit {
+    #[schemars(description = "The 'message' of the permit typed data, unchanged")]
+    pub message: serde_json::Value,
+    #[schemars(description = "The EIP-712 signature of the permit typed data, as 0x-prefixed hex")]
+    pub signature: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct GasCost {
+    /// Base fee predicted for the next block, in gwei.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub base_fee_gwei: Decimal,
+    /// Suggested priority fee (median recent tip from `eth_feeHistory`), in gwei.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub priority_fee_gwei: Decimal,
+    /// gas_estimate * (base fee + priority fee), in the native asset.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub cost_native: Decimal,
+    /// The same cost in to_token, priced through the best route from the wrapped native token;
+    /// absent when no route exists.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub cost_in_to_token: Option<Decimal>,
+    /// Set on rollups whose L1 data fee is not included in these costs (nor in net_amount_to).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub excludes_l1_data_fee: bool,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct SwapResponse {
+    pub mode: SwapMode,
     // Serialize as string to avoid precision loss.
     #[serde(with = "rust_decimal::serde::str")]
+    pub amount_from: Decimal,
+    #[serde(with = "rust_decimal::serde::str")]
     pub amount_to: Decimal,
+    // Maximum input enforced by the router; only set for exact_output swaps.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub amount_in_maximum: Option<Decimal>,
+    // Minimum output enforced by the router; only set for exact_input swaps.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub amount_out_minimum: Option<Decimal>,
+    /// Whether the non-exact amount was measured in the simulation or only quoted.
+    #[serde(default)]
+    pub amount_source: AmountSource,
+    /// Mid price of the route before fees and price impact, in to_token per from_token.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub mid_price: Decimal,
+    /// Price actually obtained: amount_to / amount_from.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub execution_price: Decimal,
+    /// Shortfall of execution_price against mid_price after fee_percent, in percent.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub price_impact_percent: Decimal,
+    /// Fee tier of each hop of the chosen route, in hundredths of a bip (e.g., 3000 = 0.3%).
+    pub fee_tiers: Vec<u32>,
+    /// Total fee charged along the route, in percent.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub fee_percent: Decimal,
     pub gas_estimate: u64,
+    /// Gas fees and the estimated cost of the swap.
+    pub gas_cost: GasCost,
+    // amount_to minus the gas cost in to_token; absent when the gas cost could not be priced.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub net_amount_to: Option<Decimal>,
+    /// Chosen route, one entry per pool hop.
+    pub route: Vec<RouteHop>,
+    /// Venue of the chosen route (e.g., 'uniswap_v3', 'uniswap_v2', 'sushiswap').
+    pub venue: String,
+    /// Best quote on each venue: output for exact_input swaps, required input for exact_output swaps.
+    pub venue_quotes: Vec<VenueQuote>,
+    /// Router the swap was simulated through.
+    pub router: SwapRouter,
+    /// Address of that router, i.e. the transaction target.
+    pub router_address: Address,
+    /// Calldata of the simulated router call.
+    pub calldata: Bytes,
+    /// Native asset sent with the call, in wei; non-zero only when swapping from the native asset.
+    pub value: U256,
+    /// Block the swap was simulated at; absent when simulating against the latest state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<PinnedBlock>,
+    /// Unsigned transactions to send in order: approvals still needed, then the swap. Only set
+    /// when build_transaction is requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transactions: Vec<UnsignedTransaction>,
+    /// Permit2 `PermitSingle` for the Universal Router as EIP-712 typed data. Signing it and passing
+    /// it back as signed_permit replaces the Permit2 approval in transactions. Only set with
+    /// build_transaction when that approval is needed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permit: Option<TypedData>,
+}
+
+/// A simulated swap, with what is needed to send the same transaction for real.
+#[derive(Debug)]
+pub struct SimulatedSwap {
+    pub response: SwapResponse,
+    pub chain: &'static ChainProfile,
+    /// Address the swap was simulated from, which is also its recipient.
+    pub sender: Address,
+    /// ERC20 paid in; `None` when paying with the native asset.
+    pub token_in: Option<Address>,
+    /// ERC20 received; `None` when receiving the native asset.
+    pub token_out: Option<Address>,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
+    /// Most `token_in` the swap may pull: the exact input, or the slippage-protected maximum.
+    pub max_input: U256,
+    /// Contract the input token must be approved to: Permit2 for the Universal Router, otherwise
+    /// the router itself. `None` for native input.
+    pub spender: Option<Address>,
+    /// Slippage tolerance the swap's limits were computed with, in percent.
+    pub slippage_percent: Decimal,
+    /// Fees the gas cost was priced at.
+    pub fees: FeeEstimate,
+    // What the calldata was encoded from, to encode it again with a signed permit.
+    route: Route,
+    native: NativeSide,
+    amount: U256,
+    limit: U256,
+}
+
+impl SimulatedSwap {
+    /// Whether the router pulls the input through Permit2: Universal Router swaps paying with an
+    /// ERC20. Their calldata carries no permit, so the router needs a Permit2 allowance on chain
+    /// or a permit signed by the sender.
+    pub fn uses_permit2(&self) -> bool {
+        self.response.router == SwapRouter::UniversalRouter && self.token_in.is_some()
+    }
+
+    /// The swap's calldata with `permit`, signed by the sender, prepended as a `PERMIT2_PERMIT`
+    /// command.
+    pub fn calldata_with_permit(&self, permit: PermitSingle, signature: Bytes) -> Result<Bytes> {
+        if !self.uses_permit2() {
+            bail!("Only Universal Router swaps of an ERC20 input take a Permit2 permit");
+        }
+        encode_universal_router_call(
+            &self.route,
+            self.response.mode,
+            self.native,
+            self.sender,
+            self.amount,
+            self.limit,
+            Some((permit, signature)),
+        )
+    }
 }
 
 pub async fn swap_tokens(
-    from_token: String,
-    to_token: String,
-    amount_from: String,
-    slippage_percent: String,
+    providers: &Providers,
+    accounts: &Accounts,
+    request: SwapRequest,
 ) -> Result<SwapResponse> {
-    tracing::trace!("Creating provider");
-    let provider = make_provider()?;
+    Ok(simulate_swap(providers, accounts, request).await?.response)
+}
 
-    tracing::debug!("Resolving tokens: {} -> {}", from_token, to_token);
-    let from_token_addr = resolve_token(&from_token).await?;
-    let to_token_addr = resolve_token(&to_token).await?;
-    tracing::trace!("From token address: {}, To token address: {}", from_token_addr, to_token_addr);
+/// Find the best route for a swap and simulate it through the chosen router.
+pub async fn simulate_swap(
+    providers: &Providers,
+    accounts: &Accounts,
+    request: SwapRequest,
+) -> Result<SimulatedSwap> {
+    let SwapRequest {
+        from_token,
+        to_token,
+        amount_from,
+        amount_to,
+        mode,
+        slippage_percent,
+        from_address,
+        account,
+        chain,
+        router,
+        block,
+        build_transaction,
+        signed_permit,
+    } = request;
+
+    let mode = resolve_swap_mode(mode, amount_from.as_deref(), amount_to.as_deref())?;
+    let build_transaction = build_transaction.unwrap_or(false);
+    if build_transaction && block.is_some() {
+        bail!("build_transaction builds transactions for the latest state and cannot be combined with block");
+    }
+    if signed_permit.is_some() && !build_transaction {
+        bail!("signed_permit is only used with build_transaction");
+    }
+    tracing::trace!("Swap mode: {:?}", mode);
 
-    let from_contract = IERC20::new(from_token_addr, &provider);
-    let to_contract = IERC20::new(to_token_addr, &provider);
+    let chain = resolve_chain(chain.as_deref())?;
+    tracing::trace!("Using provider for chain: {}", chain.name);
+    let provider = providers.get(chain)?;
+    let block = pin_block(provider, block.as_deref()).await?;
+    let block_id = block_id(block.as_ref());
+
+    tracing::debug!("Resolving tokens: {} -> {}", from_token, to_token);
+    let native = native_side(chain, &from_token, &to_token)?;
+    let from_token_addr = resolve_swap_token(providers, &from_token, chain).await?;
+    let to_token_addr = resolve_swap_token(providers, &to_token, chain).await?;
+    tracing::trace!("From token address: {}, To token address: {}, native: {:?}", from_token_addr, to_token_addr, native);
+    if from_token_addr == to_token_addr {
+        bail!("from_token and to_token both resolve to {}", from_token_addr);
+    }
 
     tracing::trace!("Fetching token decimals");
-    let (from_decimals, to_decimals) =
-        tokio::try_join!(async { from_contract.decimals().call().await }, async {
-            to_contract.decimals().call().await
-        },)
-        .context("Failed to fetch token decimals")?;
+    let (from_metadata, to_metadata) = tokio::try_join!(
+        token_metadata(provider, chain.chain_id, from_token_addr),
+        token_metadata(provider, chain.chain_id, to_token_addr),
+    )?;
+    let (from_decimals, to_decimals) = (from_metadata.decimals, to_metadata.decimals);
     tracing::trace!("From decimals: {}, To decimals: {}", from_decimals, to_decimals);
 
-    // Convert amount_from (string) to Decimal, then to U256
-    tracing::trace!("Parsing input amount: {}", amount_from);
-    let amount_from_decimal =
-        Decimal::from_str(&amount_from).context(format!("Invalid amount_from: {}", amount_from))?;
+    let slippage = Decimal::from_str(&slippage_percent)
+        .context(format!("Invalid slippage_percent: {}", slippage_percent))?;
+
+    // Get wallet address for state override
+    let wallet_addr = simulation_address(providers, accounts, chain, from_address, account).await?;
+    tracing::trace!("Wallet address for simulation: {}", wallet_addr);
 
-    // Convert to U256, using the helper function
-    let amount_from_u256 = decimal_to_u256(amount_from_decimal, from_decimals)?;
-    tracing::trace!("Input amount in U256: {}", amount_from_u256);
+    // Quote the exact side of the swap and work out the slippage-protected limit on the other side.
+    let (exact_amount, exact_decimal, search, limit) = match mode {
+        SwapMode::ExactInput => {
+            // Convert amount_from (string) to Decimal, then to U256
+            let amount_from = amount_from.unwrap_or_default();
+            tracing::trace!("Parsing input amount: {}", amount_from);
+            let amount_from_decimal = Decimal::from_str(&amount_from)
+                .context(format!("Invalid amount_from: {}", amount_from))?;
 
-    // Move quoter outside of swap_tokens function to make it clear.
-    // Use Quoter to find the best fee tier and estimate the output
-    tracing::debug!("Finding best fee tier for swap {} -> {}", from_token, to_token);
-    let (best_fee, best_amount_out) =
-        get_best_fee_and_amount_out(from_token_addr, to_token_addr, amount_from_u256, &provider)
+            // Convert to U256, using the helper function
+            let amount_from_u256 = decimal_to_u256(amount_from_decimal, from_decimals)?;
+            tracing::trace!("Input amount in U256: {}", amount_from_u256);
+
+            // Use the quoters to find the best route (V3 or V2, direct or multi-hop) and estimate the output
+            tracing::debug!("Finding best route for swap {} -> {}", from_token, to_token);
+            let search = find_best_route_exact_input(
+                chain,
+                provider,
+                block_id,
+                from_token_addr,
+                to_token_addr,
+                amount_from_u256,
+            )
             .await?;
-    tracing::debug!("Selected fee tier: {:?}, estimated output: {}", best_fee, best_amount_out);
 
-    // Calculate amountOutMinimum (considering slippage)
-    let slippage = Decimal::from_str(&slippage_percent)?;
-    let slippage_multiplier = dec!(1.0) - slippage / dec!(100.0);
-    let amount_out_decimal = u256_to_decimal(best_amount_out, to_decimals)?;
-    let min_decimal = amount_out_decimal * slippage_multiplier;
-    let amount_out_minimum = decimal_to_u256(min_decimal, to_decimals)?;
-    tracing::trace!("Slippage: {}%, Min output: {}", slippage, amount_out_minimum);
+            // Calculate amountOutMinimum (considering slippage)
+            let amount_out_minimum = min_amount_with_slippage(search.best.amount, to_decimals, slippage)?;
+            tracing::trace!("Slippage: {}%, Min output: {}", slippage, amount_out_minimum);
 
-    // Get wallet address for state override
-    let wallet_addr = get_wallet_address()?;
-    tracing::trace!("Wallet address for simulation: {}", wallet_addr);
+            (amount_from_u256, amount_from_decimal, search, amount_out_minimum)
+        }
+        SwapMode::ExactOutput => {
+            let amount_to = amount_to.unwrap_or_default();
+            tracing::trace!("Parsing output amount: {}", amount_to);
+            let amount_to_decimal = Decimal::from_str(&amount_to)
+                .context(format!("Invalid amount_to: {}", amount_to))?;
+            let amount_to_u256 = decimal_to_u256(amount_to_decimal, to_decimals)?;
+            tracing::trace!("Output amount in U256: {}", amount_to_u256);
+
+            tracing::debug!("Finding best route for exact output swap {} -> {}", from_token, to_token);
+            let search = find_best_route_exact_output(
+                chain,
+                provider,
+                block_id,
+                from_token_addr,
+                to_token_addr,
+                amount_to_u256,
+            )
+            .await?;
 
-    // Use Router to simulate swap
-    let router = UniswapV3Router::new(UNISWAP_V3_ROUTER_ADDRESS, &provider);
-
-    let params = ExactInputSingleParams {
-        tokenIn: from_token_addr,
-        tokenOut: to_token_addr,
-        fee: best_fee,
-        recipient: wallet_addr,
-        amountIn: amount_from_u256,
-        amountOutMinimum: amount_out_minimum,
-        sqrtPriceLimitX96: Uint::ZERO,
+            // Calculate amountInMaximum (considering slippage)
+            let amount_in_maximum = max_amount_with_slippage(search.best.amount, from_decimals, slippage)?;
+            tracing::trace!("Slippage: {}%, Max input: {}", slippage, amount_in_maximum);
+
+            (amount_to_u256, amount_to_decimal, search, amount_in_maximum)
+        }
+    };
+    let route = &search.best.route;
+    tracing::debug!(
+        "Selected {} route: {:?}, quote: {}",
+        route.venue.name(),
+        route.hops(),
+        search.best.amount
+    );
+
+    let max_input = match mode {
+        SwapMode::ExactInput => exact_amount,
+        SwapMode::ExactOutput => limit,
+    };
+    // Native input is paid with `value`: the exact input, or the maximum input with the rest refunded.
+    let value = match native {
+        NativeSide::Input => max_input,
+        _ => U256::ZERO,
+    };
+
+    let mut state_override = if native == NativeSide::Input {
+        tracing::trace!("Creating native balance override for: {}", wallet_addr);
+        create_native_balance_override(wallet_addr)
+    } else {
+        tracing::trace!("Creating state override for token: {}", from_token_addr);
+        let mut state_override = create_token_state_override(from_token_addr, wallet_addr);
+        seed_v2_pair_balance(provider, block_id, route, &mut state_override).await?;
+        state_override
     };
 
-    tracing::trace!("Creating state override for token: {}", from_token_addr);
-    let state_override = create_token_state_override(from_token_addr, wallet_addr);
+    let router = select_router(router.unwrap_or_default(), route);
+    let (router_address, calldata) = match router {
+        SwapRouter::UniversalRouter => {
+            // The router wraps native input itself; otherwise Permit2 pulls the input.
+            if native != NativeSide::Input {
+                override_permit2_allowance(&mut state_override, chain, wallet_addr, from_token_addr);
+            }
+            let calldata = encode_universal_router_call(
+                route,
+                mode,
+                native,
+                wallet_addr,
+                exact_amount,
+                limit,
+                None,
+            )?;
+            (chain.universal_router, calldata)
+        }
+        SwapRouter::VenueRouter => {
+            encode_swap_call(route, mode, native, wallet_addr, exact_amount, limit, chain)?
+        }
+    };
 
-    tracing::debug!("Simulating swap on Uniswap V3 Router");
-    let gas_estimate = router
-        .exactInputSingle(params.clone())
+    tracing::debug!("Simulating swap on {} via {:?} {}", route.venue.name(), router, router_address);
+    let fee_block = block.map_or(BlockNumberOrTag::Latest, |block| block.number.into());
+    let tx = TransactionRequest::default()
         .from(wallet_addr)
-        .state(state_override.clone())
-        .estimate_gas()
+        .to(router_address)
+        .value(value)
+        .input(calldata.clone().into());
+    let simulation = async {
+        match router {
+            SwapRouter::VenueRouter => {
+                let (output, gas_estimate) =
+                    simulate_router_call(provider, block_id, tx, state_override).await?;
+                let simulated = decode_swap_result(route, mode, native, &output)?;
+                Ok((simulated, AmountSource::Simulation, gas_estimate))
+            }
+            // `execute` returns nothing, so the amount is read from the sender's balance: of the
+            // output for exact input swaps, of the input for exact output swaps.
+            SwapRouter::UniversalRouter => {
+                let token = match mode {
+                    SwapMode::ExactInput => (native != NativeSide::Output).then_some(to_token_addr),
+                    SwapMode::ExactOutput => (native != NativeSide::Input).then_some(from_token_addr),
+                };
+                let quote = search.best.amount;
+                simulate_balance_change(provider, block_id, tx, state_override, token, mode, quote).await
+            }
+        }
+    };
+    let ((simulated, amount_source, gas_estimate), mid_price, fees) = tokio::try_join!(
+        simulation,
+        route_mid_price(chain, provider, block_id, route, from_decimals, to_decimals),
+        estimate_fees(provider, fee_block),
+    )?;
+    tracing::debug!("Swap simulation successful, simulated amount: {} ({:?})", simulated, amount_source);
+
+    let (venue_decimals, amount_from, amount_to, amount_in_maximum, amount_out_minimum) = match mode {
+        SwapMode::ExactInput => (
+            to_decimals,
+            exact_decimal,
+            u256_to_decimal(simulated, to_decimals)?,
+            None,
+            Some(u256_to_decimal(limit, to_decimals)?),
+        ),
+        SwapMode::ExactOutput => (
+            from_decimals,
+            u256_to_decimal(simulated, from_decimals)?,
+            exact_decimal,
+            Some(u256_to_decimal(limit, from_decimals)?),
+            None,
+        ),
+    };
+
+    let execution_price = execution_price(amount_from, amount_to)?;
+    let fee_tiers: Vec<u32> = route.hops().iter().map(|hop| hop.fee).collect();
+    let fee_percent = route_fee_percent(&fee_tiers);
+    let price_impact_percent = price_impact_percent(mid_price, execution_price, fee_percent);
+    tracing::debug!("Mid price: {}, execution price: {}, price impact: {}%", mid_price, execution_price, price_impact_percent);
+
+    let gas_cost =
+        gas_cost(chain, provider, block_id, fees, gas_estimate, to_token_addr, to_decimals).await?;
+    let net_amount_to = gas_cost.cost_in_to_token.map(|cost| amount_to - cost);
+    tracing::debug!("Gas cost: {} {}, net amount out: {:?}", gas_cost.cost_native, chain.native_symbol, net_amount_to);
+
+    let spender = match (native, router) {
+        (NativeSide::Input, _) => None,
+        (_, SwapRouter::UniversalRouter) => Some(PERMIT2),
+        (_, SwapRouter::VenueRouter) => Some(router_address),
+    };
+    let response = SwapResponse {
+        mode,
+        amount_from,
+        amount_to,
+        amount_in_maximum,
+        amount_out_minimum,
+        amount_source,
+        mid_price,
+        execution_price,
+        price_impact_percent,
+        fee_percent,
+        fee_tiers,
+        gas_estimate,
+        gas_cost,
+        net_amount_to,
+        route: route.hops(),
+        venue: route.venue.name().to_string(),
+        venue_quotes: venue_quotes(&search, venue_decimals)?,
+        router,
+        router_address,
+        calldata,
+        value,
+        block,
+        transactions: Vec::new(),
+        permit: None,
+    };
+    let mut swap = SimulatedSwap {
+        response,
+        chain,
+        sender: wallet_addr,
+        token_in: (native != NativeSide::Input).then_some(from_token_addr),
+        token_out: (native != NativeSide::Output).then_some(to_token_addr),
+        from_decimals,
+        to_decimals,
+        max_input,
+        spender,
+        slippage_percent: slippage,
+        fees,
+        route: route.clone(),
+        native,
+        amount: exact_amount,
+        limit,
+    };
+    if build_transaction {
+        tracing::debug!("Building unsigned transactions for {}", wallet_addr);
+        let permit = match &signed_permit {
+            Some(signed) => Some(check_signed_permit(provider, &swap, signed).await?),
+            None => None,
+        };
+        swap.response.transactions = build_transactions(provider, &swap, permit).await?;
+        // The sender may sign a permit and call again with it instead of sending the Permit2
+        // approval.
+        let permit2_approve = TransactionKind::Permit2Approve;
+        if swap.response.transactions.iter().any(|tx| tx.kind == permit2_approve) {
+            let permit = permit2_permit(provider, &swap).await?;
+            swap.response.permit = permit.map(|permit| permit_typed_data(chain, &permit));
+        }
+    }
+    Ok(swap)
+}
+
+fn native_side(chain: &ChainProfile, from_token: &str, to_token: &str) -> Result<NativeSide> {
+    match (chain.is_native(from_token), chain.is_native(to_token)) {
+        (true, true) => bail!("Cannot swap {} for itself", chain.native_symbol),
+        (true, false) => Ok(NativeSide::Input),
+        (false, true) => Ok(NativeSide::Output),
+        (false, false) => Ok(NativeSide::Neither),
+    }
+}
+
+/// Resolve a swap token, routing the native asset through the chain's wrapped token.
+async fn resolve_swap_token(
+    providers: &Providers,
+    token: &str,
+    chain: &'static ChainProfile,
+) -> Result<Address> {
+    if chain.is_native(token) {
+        tracing::trace!("Token {} is the native asset, routing via {}", token, chain.weth);
+        return Ok(chain.weth);
+    }
+    resolve_token_or_name(providers, chain, token).await
+}
+
+/// Price `gas_estimate` at `fees`, in the native asset and in `to_token`.
+async fn gas_cost(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    fees: FeeEstimate,
+    gas_estimate: u64,
+    to_token: Address,
+    to_decimals: u8,
+) -> Result<GasCost> {
+    let cost_wei = fees.cost(gas_estimate);
+    let cost_in_to_token = if to_token == chain.weth || cost_wei.is_zero() {
+        Some(u256_to_decimal(cost_wei, to_decimals)?)
+    } else {
+        let search =
+            find_best_route_exact_input(chain, provider, block, chain.weth, to_token, cost_wei);
+        match search.await {
+            Ok(search) => Some(u256_to_decimal(search.best.amount, to_decimals)?),
+            Err(e) => {
+                tracing::warn!("Failed to price gas cost in {}: {}", to_token, e);
+                None
+            }
+        }
+    };
+
+    Ok(GasCost {
+        base_fee_gwei: wei_to_gwei(fees.base_fee_per_gas)?,
+        priority_fee_gwei: wei_to_gwei(fees.priority_fee_per_gas)?,
+        cost_native: u256_to_decimal(cost_wei, NATIVE_DECIMALS)?,
+        cost_in_to_token,
+        excludes_l1_data_fee: chain.l1_data_fee,
+    })
+}
+
+/// Use the requested router unless the Universal Router cannot reach the route's venue.
+fn select_router(requested: SwapRouter, route: &Route) -> SwapRouter {
+    match (requested, route.venue) {
+        (SwapRouter::UniversalRouter, Venue::V2(venue)) if !venue.universal_router => {
+            tracing::debug!("Universal Router does not support {}, using its own router", venue.name);
+            SwapRouter::VenueRouter
+        }
+        (requested, _) => requested,
+    }
+}
+
+/// Let the Universal Router pull any amount of `token` from `owner` through Permit2, by injecting
+/// the allowance into Permit2's storage. Simulations never sign a permit.
+fn override_permit2_allowance(
+    state_override: &mut StateOverride,
+    chain: &ChainProfile,
+    owner: Address,
+    token: Address,
+) {
+    tracing::trace!("Overriding Permit2 allowance of {} for {}", owner, token);
+    let slot = permit2_allowance_slot(owner, token, chain.universal_router);
+    state_override
+        .entry(PERMIT2)
+        .or_default()
+        .state_diff
+        .get_or_insert_default()
+        .insert(slot, unlimited_permit2_allowance());
+}
+
+/// Encode a Universal Router `execute` call for `route`, optionally preceded by a Permit2 permit.
+/// Native input is wrapped with `WRAP_ETH` first, and native output unwrapped with `UNWRAP_WETH`.
+fn encode_universal_router_call(
+    route: &Route,
+    mode: SwapMode,
+    native: NativeSide,
+    recipient: Address,
+    amount: U256,
+    limit: U256,
+    permit: Option<(PermitSingle, Bytes)>,
+) -> Result<Bytes> {
+    let mut call = UniversalRouterCall::new();
+    if let Some((permit, signature)) = permit {
+        call.permit2_permit(permit, signature);
+    }
+
+    // Wrapped native input is paid by the router; otherwise Permit2 pulls it from the user.
+    let payer_is_user = native != NativeSide::Input;
+    if native == NativeSide::Input {
+        let wrap_amount = match mode {
+            SwapMode::ExactInput => amount,
+            SwapMode::ExactOutput => limit,
+        };
+        call.wrap_eth(ADDRESS_THIS, wrap_amount);
+    }
+    let swap_recipient = match native {
+        NativeSide::Output => ADDRESS_THIS,
+        _ => recipient,
+    };
+
+    match (route.venue, mode) {
+        (Venue::UniswapV3, SwapMode::ExactInput) => {
+            call.v3_swap_exact_in(swap_recipient, amount, limit, route.encode_path(), payer_is_user)
+        }
+        (Venue::UniswapV3, SwapMode::ExactOutput) => call.v3_swap_exact_out(
+            swap_recipient,
+            amount,
+            limit,
+            route.encode_reversed_path(),
+            payer_is_user,
+        ),
+        (Venue::V2(_), SwapMode::ExactInput) => {
+            call.v2_swap_exact_in(swap_recipient, amount, limit, route.tokens.clone(), payer_is_user)
+        }
+        (Venue::V2(_), SwapMode::ExactOutput) => {
+            call.v2_swap_exact_out(swap_recipient, amount, limit, route.tokens.clone(), payer_is_user)
+        }
+    };
+
+    match (native, mode) {
+        // Refund whatever wrapped input the exact output swap did not use.
+        (NativeSide::Input, SwapMode::ExactOutput) => {
+            call.unwrap_weth(MSG_SENDER, U256::ZERO);
+        }
+        (NativeSide::Output, SwapMode::ExactInput) => {
+            call.unwrap_weth(recipient, limit);
+        }
+        (NativeSide::Output, SwapMode::ExactOutput) => {
+            call.unwrap_weth(recipient, amount);
+        }
+        _ => {}
+    }
+
+    Ok(call.encode(swap_deadline()?))
+}
+
+/// Encode the router call for `route`, returning the router address and calldata.
+/// `amount` is the exact side of the swap and `limit` the slippage bound on the other side.
+fn encode_swap_call(
+    route: &Route,
+    mode: SwapMode,
+    native: NativeSide,
+    recipient: Address,
+    amount: U256,
+    limit: U256,
+    chain: &ChainProfile,
+) -> Result<(Address, Bytes)> {
+    let calldata = match route.venue {
+        Venue::UniswapV3 => encode_v3_swap_call(route, mode, native, recipient, amount, limit)?,
+        Venue::V2(_) => encode_v2_swap_call(route, mode, native, recipient, amount, limit)?,
+    };
+
+    let router = match route.venue {
+        Venue::UniswapV3 => chain.v3_router,
+        Venue::V2(venue) => venue.router,
+    };
+    Ok((router, calldata.into()))
+}
+
+/// Encode a `SwapRouter02` swap. Native legs are wrapped in a `multicall` with `refundETH`
+/// (native input) or `unwrapWETH9` (native output, with the swap paying the router itself).
+fn encode_v3_swap_call(
+    route: &Route,
+    mode: SwapMode,
+    native: NativeSide,
+    recipient: Address,
+    amount: U256,
+    limit: U256,
+) -> Result<Vec<u8>> {
+    let swap_recipient = match native {
+        NativeSide::Output => ADDRESS_THIS,
+        _ => recipient,
+    };
+    let swap = match mode {
+        SwapMode::ExactInput if route.is_single_hop() => UniswapV3Router::exactInputSingleCall {
+            params: ExactInputSingleParams {
+                tokenIn: route.token_in(),
+                tokenOut: route.token_out(),
+                fee: route.fees[0],
+                recipient: swap_recipient,
+                amountIn: amount,
+                amountOutMinimum: limit,
+                sqrtPriceLimitX96: Uint::ZERO,
+            },
+        }
+        .abi_encode(),
+        SwapMode::ExactInput => UniswapV3Router::exactInputCall {
+            params: ExactInputParams {
+                path: route.encode_path(),
+                recipient: swap_recipient,
+                amountIn: amount,
+                amountOutMinimum: limit,
+            },
+        }
+        .abi_encode(),
+        SwapMode::ExactOutput if route.is_single_hop() => UniswapV3Router::exactOutputSingleCall {
+            params: ExactOutputSingleParams {
+                tokenIn: route.token_in(),
+                tokenOut: route.token_out(),
+                fee: route.fees[0],
+                recipient: swap_recipient,
+                amountOut: amount,
+                amountInMaximum: limit,
+                sqrtPriceLimitX96: Uint::ZERO,
+            },
+        }
+        .abi_encode(),
+        SwapMode::ExactOutput => UniswapV3Router::exactOutputCall {
+            params: ExactOutputParams {
+                path: route.encode_reversed_path(),
+                recipient: swap_recipient,
+                amountOut: amount,
+                amountInMaximum: limit,
+            },
+        }
+        .abi_encode(),
+    };
+
+    let follow_up = match native {
+        NativeSide::Neither => return Ok(swap),
+        NativeSide::Input => UniswapV3Router::refundETHCall {}.abi_encode(),
+        NativeSide::Output => {
+            let amount_minimum = match mode {
+                SwapMode::ExactInput => limit,
+                SwapMode::ExactOutput => amount,
+            };
+            UniswapV3Router::unwrapWETH9_0Call {
+                amountMinimum: amount_minimum,
+                recipient,
+            }
+            .abi_encode()
+        }
+    };
+
+    Ok(UniswapV3Router::multicall_1Call {
+        deadline: swap_deadline()?,
+        data: vec![swap.into(), follow_up.into()],
+    }
+    .abi_encode())
+}
+
+/// Encode a V2 router swap, using the router's ETH variants for native legs.
+fn encode_v2_swap_call(
+    route: &Route,
+    mode: SwapMode,
+    native: NativeSide,
+    recipient: Address,
+    amount: U256,
+    limit: U256,
+) -> Result<Vec<u8>> {
+    let path = route.tokens.clone();
+    let deadline = swap_deadline()?;
+    let calldata = match (native, mode) {
+        (NativeSide::Neither, SwapMode::ExactInput) => UniswapV2Router::swapExactTokensForTokensCall {
+            amountIn: amount,
+            amountOutMin: limit,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+        (NativeSide::Neither, SwapMode::ExactOutput) => UniswapV2Router::swapTokensForExactTokensCall {
+            amountOut: amount,
+            amountInMax: limit,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+        (NativeSide::Input, SwapMode::ExactInput) => UniswapV2Router::swapExactETHForTokensCall {
+            amountOutMin: limit,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+        // The router refunds any native input left over.
+        (NativeSide::Input, SwapMode::ExactOutput) => UniswapV2Router::swapETHForExactTokensCall {
+            amountOut: amount,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+        (NativeSide::Output, SwapMode::ExactInput) => UniswapV2Router::swapExactTokensForETHCall {
+            amountIn: amount,
+            amountOutMin: limit,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+        (NativeSide::Output, SwapMode::ExactOutput) => UniswapV2Router::swapTokensForExactETHCall {
+            amountOut: amount,
+            amountInMax: limit,
+            path,
+            to: recipient,
+            deadline,
+        }
+        .abi_encode(),
+    };
+    Ok(calldata)
+}
+
+/// Decode the simulated amount: output for exact input swaps, input for exact output swaps.
+fn decode_swap_result(route: &Route, mode: SwapMode, native: NativeSide, output: &Bytes) -> Result<U256> {
+    let amount = match route.venue {
+        Venue::UniswapV3 if native == NativeSide::Neither => U256::abi_decode(output)?,
+        // The swap is the first call of the multicall.
+        Venue::UniswapV3 => {
+            let results = UniswapV3Router::multicall_1Call::abi_decode_returns(output)?;
+            U256::abi_decode(results.first().context("Empty multicall result")?)?
+        }
+        // Every V2 swap function returns the amounts along the path.
+        Venue::V2(_) => {
+            let amounts = Vec::<U256>::abi_decode(output)?;
+            match mode {
+                SwapMode::ExactInput => amounts.last().copied().context("Empty swap result")?,
+                SwapMode::ExactOutput => amounts.first().copied().context("Empty swap result")?,
+            }
+        }
+    };
+    Ok(amount)
+}
+
+fn venue_quotes(search: &RouteSearch, decimals: u8) -> Result<Vec<VenueQuote>> {
+    search
+        .venues
+        .iter()
+        .map(|q| {
+            Ok(VenueQuote {
+                venue: q.route.venue.name().to_string(),
+                amount: u256_to_decimal(q.amount, decimals)?,
+            })
+        })
+        .collect()
+}
+
+pub fn swap_deadline() -> Result<U256> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+    Ok(U256::from(now.as_secs() + SWAP_DEADLINE_SECS))
+}
+
+/// V2 pairs compare their absolute token balance against reserves, so the mocked input token
+/// must report the pair's real balance, not just the balance of the simulated wallet.
+async fn seed_v2_pair_balance(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    route: &Route,
+    state_override: &mut StateOverride,
+) -> Result<()> {
+    let Venue::V2(venue) = route.venue else {
+        return Ok(());
+    };
+
+    let factory = UniswapV2Router::new(venue.router, provider)
+        .factory()
+        .block(block)
+        .call()
+        .await
+        .context("Failed to fetch V2 factory")?;
+    let pair = UniswapV2Factory::new(factory, provider)
+        .getPair(route.tokens[0], route.tokens[1])
+        .block(block)
+        .call()
+        .await
+        .context("Failed to fetch V2 pair")?;
+    let balance = IERC20::new(route.tokens[0], provider)
+        .balanceOf(pair)
+        .block(block)
+        .call()
+        .await
+        .context("Failed to fetch V2 pair balance")?;
+    tracing::trace!("Seeding V2 pair {} balance: {}", pair, balance);
+
+    set_mock_balance(state_override, route.tokens[0], pair, balance);
+    Ok(())
+}
+
+/// Estimate gas for and `eth_call` a router swap, returning the raw return data and the gas estimate.
+async fn simulate_router_call(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    tx: TransactionRequest,
+    state_override: StateOverride,
+) -> Result<(Bytes, u64)> {
+    let gas_estimate = provider
+        .estimate_gas(tx.clone())
+        .block(block)
+        .overrides(state_override.clone())
         .await?;
     tracing::trace!("Gas estimate: {}", gas_estimate);
 
-    let swap_result = router
-        .exactInputSingle(params)
-        .from(wallet_addr)
-        .state(state_override)
-        .call()
+    let output = provider
+        .call(tx)
+        .block(block)
+        .overrides(state_override)
         .await
         .map_err(|e| {
             tracing::error!("Swap simulation error: {:?}", e);
             anyhow::anyhow!("Failed to simulate swap: {:?}", e)
         })?;
 
-    let amount_out = swap_result;
-    tracing::debug!("Swap simulation successful, actual output: {}", amount_out);
-
-    Ok(SwapResponse {
-        amount_to: u256_to_decimal(amount_out, to_decimals)?,
-        gas_estimate,
-    })
+    Ok((output, gas_estimate))
 }
 
-async fn get_best_fee_and_amount_out(
-    from_token_addr: Address,
-    to_token_addr: Address,
-    amount_from_u256: U256,
+/// Estimate gas for a router swap and run it in `eth_simulateV1` between two reads of the sender's
+/// balance of `token` (`None` for the native asset), returning the amount that balance changed by:
+/// received for exact input swaps, spent for exact output swaps. Unlike the quote, the measured
+/// amount reflects transfer taxes and a misencoded route. RPCs without `eth_simulateV1` get a plain
+/// `eth_call` and `quote` is reported instead.
+async fn simulate_balance_change(
     provider: &impl Provider<Ethereum>,
-) -> Result<(U24, U256)> {
-    tracing::trace!("Querying quoter for best fee tier");
-    let quoter = UniswapV3Quoter::new(UNISWAP_V3_QUOTER_ADDRESS, &provider);
+    block: BlockId,
+    tx: TransactionRequest,
+    state_override: StateOverride,
+    token: Option<Address>,
+    mode: SwapMode,
+    quote: U256,
+) -> Result<(U256, AmountSource, u64)> {
+    let gas_estimate = provider
+        .estimate_gas(tx.clone())
+        .block(block)
+        .overrides(state_override.clone())
+        .await?;
+    tracing::trace!("Gas estimate: {}", gas_estimate);
 
-    let mut best_fee = None;
-    let mut best_amount_out = U256::ZERO;
+    let holder = tx.from.context("Swap simulation needs a sender")?;
+    let read = balance_read(token, holder);
+    let calls = [read.clone(), tx.clone(), read];
+    let payload = SimulatePayload::default()
+        .extend(SimBlock::default().with_state_overrides(state_override.clone()).extend_calls(calls));
+    let results = match provider.simulate(&payload).block_id(block).await {
+        Ok(blocks) => blocks.into_iter().next().map(|block| block.calls).unwrap_or_default(),
+        Err(e) if e.is_error_resp() => {
+            tracing::warn!("eth_simulateV1 failed ({}); reporting the quote as the swap amount", e);
+            simulate_router_call(provider, block, tx, state_override).await?;
+            return Ok((quote, AmountSource::Quote, gas_estimate));
+        }
+        Err(e) => return Err(e).context("Failed to simulate swap"),
+    };
 
-    tracing::trace!("Testing fee tiers: {:?}", FEE_TIERS);
-    for &fee in &FEE_TIERS {
-        let fee_uint = Uint::<24, 1>::from_limbs([fee.into()]);
+    let [before, swap, after] = results.as_slice() else {
+        bail!("eth_simulateV1 returned {} results for 3 calls", results.len());
+    };
+    if !swap.status {
+        let reason = swap.error.as_ref().map_or("reverted", |error| error.message.as_str());
+        tracing::error!("Swap simulation error: {}", reason);
+        bail!("Failed to simulate swap: {}", reason);
+    }
+    let before = U256::abi_decode(&before.return_data).context("Failed to decode balance before the swap")?;
+    let after = U256::abi_decode(&after.return_data).context("Failed to decode balance after the swap")?;
+    tracing::trace!("Balance of {:?} before and after the swap: {} -> {}", token, before, after);
+    Ok((balance_change(mode, before, after)?, AmountSource::Simulation, gas_estimate))
+}
 
-        let result = quoter
-            .quoteExactInputSingle(
-                from_token_addr,
-                to_token_addr,
-                fee_uint,
-                amount_from_u256,
-                Uint::ZERO,
-            )
-            .call()
-            .await;
-
-        if let Ok(quote) = result {
-            let amount_out = quote;
-            tracing::trace!("Fee tier {}: quote = {}", fee, amount_out);
-            if amount_out > best_amount_out {
-                best_amount_out = amount_out;
-                best_fee = Some(fee);
-                tracing::trace!("New best fee tier: {}", fee);
-            }
-        } else {
-            tracing::trace!("Fee tier {}: no liquidity or error", fee);
+/// Call reading `holder`'s balance of `token`, or of the native asset through Multicall3.
+fn balance_read(token: Option<Address>, holder: Address) -> TransactionRequest {
+    let (to, input) = match token {
+        Some(token) => (token, IERC20::balanceOfCall { account: holder }.abi_encode()),
+        None => (MULTICALL3, Multicall3::getEthBalanceCall { addr: holder }.abi_encode()),
+    };
+    TransactionRequest::default().from(holder).to(to).input(input.into())
+}
+
+/// Amount a swap moved, from the balance before and after it: received for exact input swaps
+/// (measuring the output), spent for exact output swaps (measuring the input).
+fn balance_change(mode: SwapMode, before: U256, after: U256) -> Result<U256> {
+    let change = match mode {
+        SwapMode::ExactInput => after.checked_sub(before),
+        SwapMode::ExactOutput => before.checked_sub(after),
+    };
+    change.context(format!("Unexpected balance change in the simulated swap: {} -> {}", before, after))
+}
+
+/// Work out the swap mode from the explicit `mode` or, if omitted, from which amount was given.
+fn resolve_swap_mode(
+    mode: Option<SwapMode>,
+    amount_from: Option<&str>,
+    amount_to: Option<&str>,
+) -> Result<SwapMode> {
+    match (mode, amount_from, amount_to) {
+        (Some(SwapMode::ExactInput), Some(_), _) => Ok(SwapMode::ExactInput),
+        (Some(SwapMode::ExactInput), None, _) => bail!("amount_from is required for exact_input swaps"),
+        (Some(SwapMode::ExactOutput), _, Some(_)) => Ok(SwapMode::ExactOutput),
+        (Some(SwapMode::ExactOutput), _, None) => bail!("amount_to is required for exact_output swaps"),
+        (None, Some(_), None) => Ok(SwapMode::ExactInput),
+        (None, None, Some(_)) => Ok(SwapMode::ExactOutput),
+        (None, Some(_), Some(_)) => {
+            bail!("Both amount_from and amount_to are set; specify mode to pick one")
         }
+        (None, None, None) => bail!("Either amount_from or amount_to is required"),
     }
+}
 
-    if best_fee.is_none() {
-        tracing::warn!("No liquidity found for pair {}/{} in any V3 pool", from_token_addr, to_token_addr);
-        bail!(
-            "No liquidity found for pair {}/{} in V3 pools",
-            from_token_addr,
-            to_token_addr,
-        );
-    }
+/// Lower bound on the output after applying `slippage_percent`.
+fn min_amount_with_slippage(amount: U256, decimals: u8, slippage_percent: Decimal) -> Result<U256> {
+    let slippage_multiplier = dec!(1.0) - slippage_percent / dec!(100.0);
+    let amount_decimal = u256_to_decimal(amount, decimals)?;
+    decimal_to_u256(amount_decimal * slippage_multiplier, decimals)
+}
 
-    let best_fee = best_fee.unwrap();
-    let fee_uint = Uint::<24, 1>::from_limbs([best_fee.into()]);
+/// Upper bound on the input after applying `slippage_percent`.
+fn max_amount_with_slippage(amount: U256, decimals: u8, slippage_percent: Decimal) -> Result<U256> {
+    let slippage_multiplier = dec!(1.0) + slippage_percent / dec!(100.0);
+    let amount_decimal = u256_to_decimal(amount, decimals)?;
+    let max_decimal = (amount_decimal * slippage_multiplier)
+        .round_dp_with_strategy(decimals as u32, rust_decimal::RoundingStrategy::AwayFromZero);
+    decimal_to_u256(max_decimal, decimals)
+}
 
-    Ok((fee_uint, best_amount_out))
+async fn simulation_address(
+    providers: &Providers,
+    accounts: &Accounts,
+    chain: &'static ChainProfile,
+    from_address: Option<String>,
+    account: Option<String>,
+) -> Result<Address> {
+    match (from_address, account) {
+        (Some(_), Some(_)) => bail!("from_address and account cannot both be set"),
+        (Some(from_address), None) => resolve_address(providers, chain, &from_address)
+            .await
+            .context(format!("Invalid from_address: {}", from_address)),
+        (None, account) => Ok(accounts.get(account.as_deref())?.unwrap_or(DEFAULT_SIMULATION_ADDRESS)),
+    }
 }
 
-fn create_token_state_override(token_address: Address, signer_addr: Address) -> StateOverride {
-    let balance_slot = keccak256(
+/// Storage slot of `balanceOf[holder]` in MockToken (mapping at slot 0).
+fn mock_balance_slot(holder: Address) -> B256 {
+    keccak256(
         [
             &[0u8; 12],
-            signer_addr.as_slice(), // Pad address to 32 bytes
-            &[0u8; 32],             // Slot 0 (uint256 0 padded to 32 bytes)
+            holder.as_slice(), // Pad address to 32 bytes
+            &[0u8; 32],        // Slot 0 (uint256 0 padded to 32 bytes)
         ]
         .concat(),
-    );
+    )
+}
 
+fn create_token_state_override(token_address: Address, signer_addr: Address) -> StateOverride {
     let mut storage = AccountOverride::default().state.unwrap_or_default();
 
     // Wealthy as much as possible.
-    storage.insert(balance_slot, U256::MAX.into());
+    storage.insert(mock_balance_slot(signer_addr), U256::MAX.into());
 
     let account_override = AccountOverride {
         // Use MockToken to skip the token allowance check.
@@ -239,9 +1193,34 @@ fn create_token_state_override(token_address: Address, signer_addr: Address) ->
     state_override
 }
 
+/// Fund `holder` with enough native balance to pay any swap `value`.
+fn create_native_balance_override(holder: Address) -> StateOverride {
+    let account_override = AccountOverride {
+        // Half of the maximum leaves headroom for the recipient's balance to grow.
+        balance: Some(U256::MAX >> 1),
+        ..Default::default()
+    };
+
+    let mut state_override = StateOverride::default();
+    state_override.insert(holder, account_override);
+    state_override
+}
+
+/// Set `holder`'s MockToken balance in an override created by `create_token_state_override`.
+fn set_mock_balance(state_override: &mut StateOverride, token_address: Address, holder: Address, balance: U256) {
+    if let Some(state_diff) = state_override
+        .get_mut(&token_address)
+        .and_then(|account| account.state_diff.as_mut())
+    {
+        state_diff.insert(mock_balance_slot(holder), balance.into());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::chains::MAINNET;
+    use alloy::primitives::aliases::U24;
     use rust_decimal::Decimal;
     use serde_json;
     use std::str::FromStr;
@@ -249,19 +1228,189 @@ mod tests {
     #[test]
     fn swap_response_serde_uses_string_field() {
         let response = SwapResponse {
+            mode: SwapMode::ExactInput,
+            amount_from: Decimal::from_str("100").unwrap(),
             amount_to: Decimal::from_str("42.5").unwrap(),
+            amount_in_maximum: None,
+            amount_out_minimum: Some(Decimal::from_str("42.2875").unwrap()),
+            amount_source: AmountSource::Simulation,
+            mid_price: Decimal::from_str("0.4275").unwrap(),
+            execution_price: Decimal::from_str("0.425").unwrap(),
+            price_impact_percent: Decimal::from_str("0.5848").unwrap(),
+            fee_tiers: vec![3000],
+            fee_percent: Decimal::from_str("0.3").unwrap(),
             gas_estimate: 99,
+            gas_cost: GasCost {
+                base_fee_gwei: Decimal::from_str("12.5").unwrap(),
+                priority_fee_gwei: Decimal::from_str("0.1").unwrap(),
+                cost_native: Decimal::from_str("0.0000012474").unwrap(),
+                cost_in_to_token: Some(Decimal::from_str("0.0005").unwrap()),
+                excludes_l1_data_fee: false,
+            },
+            net_amount_to: Some(Decimal::from_str("42.4995").unwrap()),
+            route: vec![RouteHop {
+                token_in: address!("0x1000000000000000000000000000000000000000"),
+                token_out: address!("0x2000000000000000000000000000000000000000"),
+                fee: 3000,
+            }],
+            venue: "uniswap_v3".to_string(),
+            venue_quotes: vec![VenueQuote {
+                venue: "uniswap_v3".to_string(),
+                amount: Decimal::from_str("42.5").unwrap(),
+            }],
+            router: SwapRouter::UniversalRouter,
+            router_address: MAINNET.universal_router,
+            calldata: Bytes::from_static(&[0x35, 0x93, 0x56, 0x4c]),
+            value: U256::ZERO,
+            block: None,
+            transactions: Vec::new(),
+            permit: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("\"mode\":\"exact_input\""));
         assert!(json.contains("\"amount_to\":\"42.5\""));
         assert!(json.contains("\"gas_estimate\":99"));
+        assert!(json.contains("\"base_fee_gwei\":\"12.5\""));
+        assert!(json.contains("\"cost_in_to_token\":\"0.0005\""));
+        assert!(json.contains("\"net_amount_to\":\"42.4995\""));
+        assert!(!json.contains("amount_in_maximum"));
+        assert!(json.contains("\"amount_out_minimum\":\"42.2875\""));
+        assert!(json.contains("\"price_impact_percent\":\"0.5848\""));
+        assert!(json.contains("\"fee_tiers\":[3000]"));
+        assert!(json.contains("\"fee\":3000"));
+        assert!(json.contains("\"venue_quotes\":[{\"venue\":\"uniswap_v3\",\"amount\":\"42.5\"}]"));
+        assert!(json.contains("\"router\":\"universal_router\""));
+        assert!(json.contains("\"calldata\":\"0x3593564c\""));
 
         let parsed: SwapResponse = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.amount_to, response.amount_to);
         assert_eq!(parsed.gas_estimate, response.gas_estimate);
     }
 
+    #[test]
+    fn swap_response_serializes_amount_in_maximum_as_string() {
+        let response = SwapResponse {
+            mode: SwapMode::ExactOutput,
+            amount_from: Decimal::from_str("3500.25").unwrap(),
+            amount_to: Decimal::from_str("1").unwrap(),
+            amount_in_maximum: Some(Decimal::from_str("3517.75").unwrap()),
+            amount_out_minimum: None,
+            amount_source: AmountSource::Quote,
+            mid_price: Decimal::from_str("0.000286").unwrap(),
+            execution_price: Decimal::from_str("0.0002856939").unwrap(),
+            price_impact_percent: Decimal::from_str("0.107").unwrap(),
+            fee_tiers: vec![],
+            fee_percent: Decimal::ZERO,
+            gas_estimate: 150000,
+            gas_cost: GasCost {
+                base_fee_gwei: Decimal::ONE,
+                priority_fee_gwei: Decimal::ZERO,
+                cost_native: Decimal::from_str("0.00015").unwrap(),
+                cost_in_to_token: None,
+                excludes_l1_data_fee: true,
+            },
+            net_amount_to: None,
+            route: vec![],
+            venue: "sushiswap".to_string(),
+            venue_quotes: vec![],
+            router: SwapRouter::VenueRouter,
+            router_address: MAINNET.v2_venues[1].router,
+            calldata: Bytes::new(),
+            value: U256::from(1),
+            block: Some(PinnedBlock {
+                number: 19_000_000,
+                hash: B256::ZERO,
+                timestamp: 1_705_173_443,
+            }),
+            transactions: Vec::new(),
+            permit: None,
+        };
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("\"mode\":\"exact_output\""));
+        assert!(json.contains("\"amount_source\":\"quote\""));
+        assert!(!json.contains("transactions"));
+        assert!(json.contains("\"amount_in_maximum\":\"3517.75\""));
+        assert!(!json.contains("amount_out_minimum"));
+        assert!(!json.contains("cost_in_to_token"));
+        assert!(!json.contains("net_amount_to"));
+        assert!(json.contains("\"number\":19000000"));
+
+        let parsed: SwapResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.amount_in_maximum, response.amount_in_maximum);
+        assert_eq!(parsed.block, response.block);
+    }
+
+    #[test]
+    fn resolve_swap_mode_infers_from_amounts() {
+        assert_eq!(resolve_swap_mode(None, Some("1"), None).unwrap(), SwapMode::ExactInput);
+        assert_eq!(resolve_swap_mode(None, None, Some("1")).unwrap(), SwapMode::ExactOutput);
+        assert!(resolve_swap_mode(None, Some("1"), Some("1")).is_err());
+        assert!(resolve_swap_mode(None, None, None).is_err());
+    }
+
+    #[test]
+    fn resolve_swap_mode_requires_matching_amount() {
+        assert!(resolve_swap_mode(Some(SwapMode::ExactOutput), Some("1"), None).is_err());
+        assert!(resolve_swap_mode(Some(SwapMode::ExactInput), None, Some("1")).is_err());
+        assert_eq!(
+            resolve_swap_mode(Some(SwapMode::ExactOutput), Some("1"), Some("2")).unwrap(),
+            SwapMode::ExactOutput
+        );
+    }
+
+    #[test]
+    fn slippage_bounds_move_in_opposite_directions() {
+        let amount = U256::from(1_000_000u64); // 1.0 with 6 decimals
+        let slippage = Decimal::from_str("0.5").unwrap();
+        assert_eq!(
+            min_amount_with_slippage(amount, 6, slippage).unwrap(),
+            U256::from(995_000u64)
+        );
+        assert_eq!(
+            max_amount_with_slippage(amount, 6, slippage).unwrap(),
+            U256::from(1_005_000u64)
+        );
+    }
+
+    #[test]
+    fn max_amount_with_slippage_rounds_up() {
+        // 0.000001 * 1.005 = 0.000001005, which must round up to 0.000002.
+        let amount = U256::from(1u64);
+        let slippage = Decimal::from_str("0.5").unwrap();
+        assert_eq!(max_amount_with_slippage(amount, 6, slippage).unwrap(), U256::from(2u64));
+    }
+
+    #[tokio::test]
+    async fn simulation_address_prefers_from_address() {
+        let from = "0x2000000000000000000000000000000000000000".to_string();
+        let result = simulation_address(&Providers::default(), &Accounts::default(), &MAINNET, Some(from), None)
+            .await
+            .unwrap();
+        assert_eq!(result, address!("0x2000000000000000000000000000000000000000"));
+    }
+
+    #[tokio::test]
+    async fn simulation_address_rejects_from_address_with_account() {
+        let from = "0x2000000000000000000000000000000000000000".to_string();
+        let accounts = Accounts::default();
+        let providers = Providers::default();
+        let result = simulation_address(&providers, &accounts, &MAINNET, Some(from), Some("ops".to_string())).await;
+        assert!(result.is_err());
+        let result = simulation_address(&providers, &accounts, &MAINNET, None, None).await.unwrap();
+        assert_eq!(result, DEFAULT_SIMULATION_ADDRESS);
+    }
+
+    #[tokio::test]
+    async fn simulation_address_rejects_invalid_from_address() {
+        let providers = Providers::default();
+        let accounts = Accounts::default();
+        let result =
+            simulation_address(&providers, &accounts, &MAINNET, Some("not-an-address".to_string()), None).await;
+        assert!(result.is_err());
+    }
+
     #[test]
     fn create_token_state_override_adds_account_override() {
         let token = address!("0x1000000000000000000000000000000000000000");
@@ -279,4 +1428,317 @@ mod tests {
         let storage = entry.state_diff.as_ref().unwrap();
         assert!(!storage.is_empty());
     }
+
+    #[test]
+    fn set_mock_balance_adds_holder_slot() {
+        let token = address!("0x1000000000000000000000000000000000000000");
+        let signer = address!("0x2000000000000000000000000000000000000000");
+        let pair = address!("0x3000000000000000000000000000000000000000");
+
+        let mut override_map = create_token_state_override(token, signer);
+        set_mock_balance(&mut override_map, token, pair, U256::from(42));
+
+        let storage = override_map.get(&token).unwrap().state_diff.as_ref().unwrap();
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage.get(&mock_balance_slot(pair)), Some(&B256::from(U256::from(42))));
+    }
+
+    #[test]
+    fn encode_swap_call_targets_venue_router() {
+        let token_in = address!("0x1000000000000000000000000000000000000000");
+        let token_out = address!("0x2000000000000000000000000000000000000000");
+        let recipient = address!("0x3000000000000000000000000000000000000000");
+        let venue = &MAINNET.v2_venues[0];
+        let route = Route {
+            venue: Venue::V2(venue),
+            tokens: vec![token_in, token_out],
+            fees: vec![U24::from(3000)],
+        };
+
+        let (router, calldata) = encode_swap_call(
+            &route,
+            SwapMode::ExactInput,
+            NativeSide::Neither,
+            recipient,
+            U256::from(100),
+            U256::from(90),
+            &MAINNET,
+        )
+        .unwrap();
+        assert_eq!(router, venue.router);
+        let decoded = UniswapV2Router::swapExactTokensForTokensCall::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.amountIn, U256::from(100));
+        assert_eq!(decoded.amountOutMin, U256::from(90));
+        assert_eq!(decoded.path, vec![token_in, token_out]);
+        assert_eq!(decoded.to, recipient);
+
+        let v3_route = Route {
+            venue: Venue::UniswapV3,
+            ..route
+        };
+        let (router, calldata) = encode_swap_call(
+            &v3_route,
+            SwapMode::ExactOutput,
+            NativeSide::Neither,
+            recipient,
+            U256::from(100),
+            U256::from(110),
+            &MAINNET,
+        )
+        .unwrap();
+        assert_eq!(router, MAINNET.v3_router);
+        let decoded = UniswapV3Router::exactOutputSingleCall::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.params.amountOut, U256::from(100));
+        assert_eq!(decoded.params.amountInMaximum, U256::from(110));
+    }
+
+    #[test]
+    fn select_router_falls_back_for_unsupported_venue() {
+        let tokens = vec![MAINNET.weth, address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")];
+        let uniswap_v2 = Route {
+            venue: Venue::V2(&MAINNET.v2_venues[0]),
+            tokens: tokens.clone(),
+            fees: vec![U24::from(3000)],
+        };
+        let sushiswap = Route {
+            venue: Venue::V2(&MAINNET.v2_venues[1]),
+            tokens,
+            fees: vec![U24::from(3000)],
+        };
+
+        assert_eq!(
+            select_router(SwapRouter::UniversalRouter, &uniswap_v2),
+            SwapRouter::UniversalRouter
+        );
+        assert_eq!(
+            select_router(SwapRouter::UniversalRouter, &sushiswap),
+            SwapRouter::VenueRouter
+        );
+        assert_eq!(
+            select_router(SwapRouter::VenueRouter, &uniswap_v2),
+            SwapRouter::VenueRouter
+        );
+    }
+
+    #[test]
+    fn encode_universal_router_call_prepends_permit() {
+        use crate::utils::contracts::UniswapUniversalRouter;
+        use crate::utils::universal_router::{PERMIT2_PERMIT, V3_SWAP_EXACT_OUT, permit_single};
+        use alloy::primitives::aliases::U48;
+
+        let token_in = address!("0x1000000000000000000000000000000000000000");
+        let token_out = address!("0x2000000000000000000000000000000000000000");
+        let recipient = address!("0x3000000000000000000000000000000000000000");
+        let route = Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![token_in, token_out],
+            fees: vec![U24::from(500)],
+        };
+        let permit = permit_single(
+            token_in,
+            U256::from(110),
+            1,
+            U48::ZERO,
+            MAINNET.universal_router,
+            U256::from(1),
+        );
+
+        let calldata = encode_universal_router_call(
+            &route,
+            SwapMode::ExactOutput,
+            NativeSide::Neither,
+            recipient,
+            U256::from(100),
+            U256::from(110),
+            Some((permit, Bytes::from(vec![0u8; 65]))),
+        )
+        .unwrap();
+        let decoded = UniswapUniversalRouter::execute_1Call::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.commands.as_ref(), &[PERMIT2_PERMIT, V3_SWAP_EXACT_OUT]);
+
+        let (to, amount_out, amount_in_max, path, payer_is_user) =
+            <(Address, U256, U256, Bytes, bool)>::abi_decode_params(&decoded.inputs[1]).unwrap();
+        assert_eq!(to, recipient);
+        assert_eq!(amount_out, U256::from(100));
+        assert_eq!(amount_in_max, U256::from(110));
+        assert_eq!(path, route.encode_reversed_path());
+        assert!(payer_is_user);
+    }
+
+    #[test]
+    fn native_side_detects_native_symbol() {
+        assert_eq!(native_side(&MAINNET, "ETH", "USDC").unwrap(), NativeSide::Input);
+        assert_eq!(native_side(&MAINNET, "USDC", "eth").unwrap(), NativeSide::Output);
+        assert_eq!(native_side(&MAINNET, "WETH", "USDC").unwrap(), NativeSide::Neither);
+        assert!(native_side(&MAINNET, "ETH", "ETH").is_err());
+    }
+
+    #[test]
+    fn encode_swap_call_uses_v2_eth_variants() {
+        let token_out = address!("0x2000000000000000000000000000000000000000");
+        let recipient = address!("0x3000000000000000000000000000000000000000");
+        let route = Route {
+            venue: Venue::V2(&MAINNET.v2_venues[0]),
+            tokens: vec![MAINNET.weth, token_out],
+            fees: vec![U24::from(3000)],
+        };
+
+        let (_, calldata) = encode_swap_call(
+            &route,
+            SwapMode::ExactOutput,
+            NativeSide::Input,
+            recipient,
+            U256::from(100),
+            U256::from(110),
+            &MAINNET,
+        )
+        .unwrap();
+        let decoded = UniswapV2Router::swapETHForExactTokensCall::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.amountOut, U256::from(100));
+        assert_eq!(decoded.path, vec![MAINNET.weth, token_out]);
+    }
+
+    #[test]
+    fn encode_swap_call_unwraps_native_output_in_multicall() {
+        let token_in = address!("0x1000000000000000000000000000000000000000");
+        let recipient = address!("0x3000000000000000000000000000000000000000");
+        let route = Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![token_in, MAINNET.weth],
+            fees: vec![U24::from(500)],
+        };
+
+        let (_, calldata) = encode_swap_call(
+            &route,
+            SwapMode::ExactInput,
+            NativeSide::Output,
+            recipient,
+            U256::from(100),
+            U256::from(90),
+            &MAINNET,
+        )
+        .unwrap();
+        let multicall = UniswapV3Router::multicall_1Call::abi_decode(&calldata).unwrap();
+        assert_eq!(multicall.data.len(), 2);
+
+        let swap = UniswapV3Router::exactInputSingleCall::abi_decode(&multicall.data[0]).unwrap();
+        assert_eq!(swap.params.recipient, ADDRESS_THIS);
+        let unwrap = UniswapV3Router::unwrapWETH9_0Call::abi_decode(&multicall.data[1]).unwrap();
+        assert_eq!(unwrap.amountMinimum, U256::from(90));
+        assert_eq!(unwrap.recipient, recipient);
+    }
+
+    #[test]
+    fn encode_universal_router_call_wraps_native_input() {
+        use crate::utils::contracts::UniswapUniversalRouter;
+        use crate::utils::universal_router::{UNWRAP_WETH, V3_SWAP_EXACT_OUT, WRAP_ETH};
+
+        let token_out = address!("0x2000000000000000000000000000000000000000");
+        let recipient = address!("0x3000000000000000000000000000000000000000");
+        let route = Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![MAINNET.weth, token_out],
+            fees: vec![U24::from(500)],
+        };
+
+        let calldata = encode_universal_router_call(
+            &route,
+            SwapMode::ExactOutput,
+            NativeSide::Input,
+            recipient,
+            U256::from(100),
+            U256::from(110),
+            None,
+        )
+        .unwrap();
+        let decoded = UniswapUniversalRouter::execute_1Call::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.commands.as_ref(), &[WRAP_ETH, V3_SWAP_EXACT_OUT, UNWRAP_WETH]);
+
+        let (to, wrap_amount) = <(Address, U256)>::abi_decode_params(&decoded.inputs[0]).unwrap();
+        assert_eq!(to, ADDRESS_THIS);
+        assert_eq!(wrap_amount, U256::from(110));
+        let (_, _, _, _, payer_is_user) =
+            <(Address, U256, U256, Bytes, bool)>::abi_decode_params(&decoded.inputs[1]).unwrap();
+        assert!(!payer_is_user);
+    }
+
+    #[test]
+    fn balance_change_measures_output_or_input() {
+        let (before, after) = (U256::from(1000), U256::from(1400));
+        assert_eq!(balance_change(SwapMode::ExactInput, before, after).unwrap(), U256::from(400));
+        assert_eq!(balance_change(SwapMode::ExactOutput, after, before).unwrap(), U256::from(400));
+        assert!(balance_change(SwapMode::ExactInput, after, before).is_err());
+    }
+
+    #[test]
+    fn balance_read_uses_multicall_for_native() {
+        let holder = address!("0x2000000000000000000000000000000000000000");
+        let native = balance_read(None, holder);
+        assert_eq!(native.to, Some(MULTICALL3.into()));
+        let input = native.input.input().unwrap();
+        assert_eq!(Multicall3::getEthBalanceCall::abi_decode(input).unwrap().addr, holder);
+
+        let token = address!("0x1000000000000000000000000000000000000000");
+        let erc20 = balance_read(Some(token), holder);
+        assert_eq!(erc20.to, Some(token.into()));
+        assert_eq!(IERC20::balanceOfCall::abi_decode(erc20.input.input().unwrap()).unwrap().account, holder);
+    }
+
+    #[test]
+    fn create_native_balance_override_funds_holder() {
+        let holder = address!("0x2000000000000000000000000000000000000000");
+        let override_map = create_native_balance_override(holder);
+        let entry = override_map.get(&holder).expect("holder override entry");
+        assert_eq!(entry.balance, Some(U256::MAX >> 1));
+        assert!(entry.code.is_none());
+    }
+
+    #[tokio::test]
+    async fn swap_tokens_does_not_build_transactions_for_past_blocks() {
+        let request = SwapRequest {
+            from_token: "ETH".to_string(),
+            to_token: "USDC".to_string(),
+            amount_from: Some("1".to_string()),
+            amount_to: None,
+            mode: None,
+            slippage_percent: "0.5".to_string(),
+            from_address: None,
+            account: None,
+            chain: None,
+            router: None,
+            block: Some("19000000".to_string()),
+            build_transaction: Some(true),
+            signed_permit: None,
+        };
+        let err = swap_tokens(&Providers::default(), &Accounts::default(), request)
+            .await
+            .expect_err("building at a past block should fail");
+        assert!(err.to_string().contains("cannot be combined with block"));
+    }
+
+    #[tokio::test]
+    async fn swap_tokens_uses_signed_permit_only_when_building_transactions() {
+        let request = SwapRequest {
+            from_token: "USDC".to_string(),
+            to_token: "ETH".to_string(),
+            amount_from: Some("100".to_string()),
+            amount_to: None,
+            mode: None,
+            slippage_percent: "0.5".to_string(),
+            from_address: None,
+            account: None,
+            chain: None,
+            router: None,
+            block: None,
+            build_transaction: None,
+            signed_permit: Some(SignedPermit {
+                message: serde_json::json!({}),
+                signature: "0x".to_string(),
+            }),
+        };
+        let err = swap_tokens(&Providers::default(), &Accounts::default(), request)
+            .await
+            .expect_err("a signed permit without build_transaction should fail");
+        assert!(err.to_string().contains("only used with build_transaction"));
+    }
 }
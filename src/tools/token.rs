@@ -0,0 +1,199 @@
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+
+use crate::utils::chains::{CHAIN_PROFILES, resolve_chain};
+use crate::utils::decimals::u256_to_decimal;
+use crate::utils::provider::Providers;
+use crate::utils::ens::resolve_token_or_name;
+use crate::utils::token_registry::{
+    self, ListedToken, OnchainMetadata, listed_metadata, onchain_metadata,
+};
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+const MAX_SEARCH_LIMIT: usize = 50;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SearchTokensRequest {
+    #[schemars(description = "Symbol or name to search for (e.g., 'usdc', 'wrapped ether'); tolerates typos")]
+    pub query: String,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; searches every chain when omitted"
+    )]
+    pub chain: Option<String>,
+    #[schemars(description = "Maximum number of results (default 10, at most 50)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TokenSearchResult {
+    /// Chain name, when the chain is supported by this server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    #[serde(flatten)]
+    pub token: ListedToken,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SearchTokensResponse {
+    pub results: Vec<TokenSearchResult>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TokenInfoRequest {
+    #[schemars(description = "Token address (e.g., '0x...'), ENS name or symbol (e.g., 'UNI')")]
+    pub token: String,
+    #[schemars(
+        description = "Chain name (e.g., 'ethereum', 'arbitrum', 'base') or chain id; defaults to Ethereum mainnet"
+    )]
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TokenInfoResponse {
+    pub chain: String,
+    pub address: Address,
+    /// Whether any contract code is deployed at the address.
+    pub has_code: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// Total supply in whole tokens; raw units when decimals are unavailable.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub total_supply: Option<Decimal>,
+    /// Token list that lists this token, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listed_in: Option<String>,
+}
+
+pub async fn search_tokens(
+    query: String,
+    chain: Option<String>,
+    limit: Option<usize>,
+) -> Result<SearchTokensResponse> {
+    let chain_id = match chain.as_deref() {
+        Some(chain) => Some(resolve_chain(Some(chain))?.chain_id),
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+
+    let tokens = token_registry::search_tokens(&query, chain_id, limit).await?;
+    let results = tokens
+        .into_iter()
+        .map(|token| TokenSearchResult {
+            chain: CHAIN_PROFILES
+                .into_iter()
+                .find(|profile| profile.chain_id == token.chain_id)
+                .map(|profile| profile.name.to_string()),
+            token,
+        })
+        .collect();
+    Ok(SearchTokensResponse { results })
+}
+
+pub async fn get_token_info(
+    providers: &Providers,
+    token: String,
+    chain: Option<String>,
+) -> Result<TokenInfoResponse> {
+    let chain = resolve_chain(chain.as_deref())?;
+    tracing::trace!("Using provider for chain: {}", chain.name);
+    let provider = providers.get(chain)?;
+
+    let address = resolve_token_or_name(providers, chain, &token).await?;
+    let (code, listed) = tokio::try_join!(
+        async {
+            provider
+                .get_code_at(address)
+                .await
+                .context(format!("Failed to fetch code at {}", address))
+        },
+        listed_metadata(chain.chain_id, address),
+    )?;
+    let listed_in = listed.map(|metadata| metadata.source);
+
+    if code.is_empty() {
+        tracing::debug!("No contract code at {}", address);
+        return Ok(TokenInfoResponse {
+            chain: chain.name.to_string(),
+            address,
+            has_code: false,
+            name: None,
+            symbol: None,
+            decimals: None,
+            total_supply: None,
+            listed_in,
+        });
+    }
+
+    // Read everything on chain; a contract that is not an ERC20 simply leaves fields empty.
+    let OnchainMetadata { name, symbol, decimals, total_supply } =
+        onchain_metadata(provider, address).await?;
+    tracing::trace!("Token {} on chain: {:?} {:?} {:?} {:?}", address, name, symbol, decimals, total_supply);
+
+    Ok(TokenInfoResponse {
+        chain: chain.name.to_string(),
+        address,
+        has_code: true,
+        name,
+        symbol,
+        decimals,
+        total_supply: total_supply
+            .map(|supply| format_supply(supply, decimals))
+            .transpose()?,
+        listed_in,
+    })
+}
+
+fn format_supply(supply: U256, decimals: Option<u8>) -> Result<Decimal> {
+    u256_to_decimal(supply, decimals.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_format_supply() {
+        let supply = U256::from(1_500_000_000u64);
+        assert_eq!(format_supply(supply, Some(6)).unwrap(), Decimal::from_str("1500").unwrap());
+        assert_eq!(format_supply(supply, None).unwrap(), Decimal::from(1_500_000_000u64));
+    }
+
+    #[tokio::test]
+    async fn test_search_tokens_names_chains() {
+        let response = search_tokens("USDC".to_string(), Some("arbitrum".to_string()), None)
+            .await
+            .unwrap();
+        let first = &response.results[0];
+        assert_eq!(first.chain.as_deref(), Some("arbitrum"));
+        assert_eq!(first.token.symbol, "USDC");
+
+        let json = serde_json::to_value(first).unwrap();
+        assert_eq!(json["chain_id"], 42161);
+        assert!(json["source"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_search_tokens_rejects_unknown_chain() {
+        let result = search_tokens("USDC".to_string(), Some("solana".to_string()), None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_token_info_unconfigured_chain_returns_error() {
+        let result =
+            get_token_info(&Providers::default(), "USDC".to_string(), Some("base".to_string())).await;
+        let err = result.expect_err("unconfigured chain should fail");
+        assert!(err.to_string().contains("not configured"));
+    }
+}
@@ -0,0 +1,329 @@
+use alloy::network::{Ethereum, TransactionBuilder};
+use alloy::primitives::aliases::{U48, U160};
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolCall;
+use anyhow::{Context, Result, bail};
+use std::str::FromStr;
+
+use crate::tools::swap::{SignedPermit, SimulatedSwap, swap_deadline};
+use crate::utils::chains::PERMIT2;
+use crate::utils::contracts::IAllowanceTransfer::PermitSingle;
+use crate::utils::contracts::{IERC20, UniswapPermit2};
+use crate::utils::gas::FeeEstimate;
+use crate::utils::universal_router::{permit_from_message, permit_single, recover_permit_signer};
+
+/// Permit2 allowances granted to the Universal Router expire after 30 days, like the Uniswap front end.
+const PERMIT_EXPIRATION_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Headroom added on top of gas estimates for the gas limit of built transactions.
+const GAS_LIMIT_BUFFER_PERCENT: u64 = 20;
+/// Gas limit of an approval whose estimate fails, e.g. because an earlier approval it depends on
+/// is not mined yet.
+const DEFAULT_APPROVAL_GAS: u64 = 100_000;
+/// Gas a `PERMIT2_PERMIT` command adds to a swap simulated without it: signature recovery and
+/// the allowance write.
+const PERMIT_GAS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    /// ERC20 `approve` of the contract that pulls the swap input (Permit2 or the router).
+    Approve,
+    /// Permit2 `approve` of the Universal Router, for senders without a signed permit.
+    Permit2Approve,
+    /// The swap itself.
+    Swap,
+}
+
+impl TransactionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionKind::Approve => "approval",
+            TransactionKind::Permit2Approve => "Permit2 approval",
+            TransactionKind::Swap => "swap",
+        }
+    }
+}
+
+/// A call the sender has to make before a swap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approval {
+    pub kind: TransactionKind,
+    pub to: Address,
+    pub data: Bytes,
+}
+
+impl Approval {
+    pub fn request(&self, from: Address) -> TransactionRequest {
+        TransactionRequest::default()
+            .with_from(from)
+            .with_to(self.to)
+            .with_input(self.data.clone())
+    }
+}
+
+/// A fully built EIP-1559 transaction, to be signed elsewhere (e.g., a Safe or a hardware wallet).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct UnsignedTransaction {
+    pub kind: TransactionKind,
+    pub chain_id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub data: Bytes,
+    /// Native asset sent, in wei.
+    pub value: U256,
+    pub nonce: u64,
+    /// Gas estimate plus a 20% buffer.
+    pub gas_limit: u64,
+    /// Twice the predicted base fee plus the priority fee, in wei, so the transaction stays
+    /// includable while the base fee rises.
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// Approvals the sender must send before `swap`, in order; empty when its allowances suffice.
+/// The input is approved for exactly the swap's maximum input, and a non-zero ERC20 allowance is
+/// reset to zero first, as tokens such as USDT require. With `signed_permit`, the swap will carry
+/// a Permit2 permit signed by the sender, which replaces the router's Permit2 allowance.
+pub async fn required_approvals(
+    provider: &impl Provider<Ethereum>,
+    swap: &SimulatedSwap,
+    signed_permit: bool,
+) -> Result<Vec<Approval>> {
+    let (Some(token), Some(spender)) = (swap.token_in, swap.spender) else {
+        return Ok(Vec::new());
+    };
+    let mut approvals = Vec::new();
+
+    let allowance = IERC20::new(token, provider)
+        .allowance(swap.sender, spender)
+        .call()
+        .await
+        .context(format!("Failed to fetch allowance of token {}", token))?;
+    tracing::trace!("Allowance of {} for {}: {}", token, spender, allowance);
+    if allowance < swap.max_input {
+        if !allowance.is_zero() {
+            approvals.push(erc20_approval(token, spender, U256::ZERO));
+        }
+        approvals.push(erc20_approval(token, spender, swap.max_input));
+    }
+
+    // Without a permit in the calldata, the Universal Router needs a Permit2 allowance on chain.
+    if swap.uses_permit2() && !signed_permit {
+        let router = swap.response.router_address;
+        let allowance = UniswapPermit2::new(PERMIT2, provider)
+            .allowance(swap.sender, token, router)
+            .call()
+            .await
+            .context("Failed to fetch Permit2 allowance")?;
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+        if U256::from(allowance.amount) < swap.max_input || allowance.expiration.to::<u64>() <= now {
+            let approve = UniswapPermit2::approveCall {
+                token,
+                spender: router,
+                amount: U160::saturating_from(swap.max_input),
+                expiration: U48::saturating_from(now + PERMIT_EXPIRATION_SECS),
+            };
+            approvals.push(Approval {
+                kind: TransactionKind::Permit2Approve,
+                to: PERMIT2,
+                data: approve.abi_encode().into(),
+            });
+        }
+    }
+    Ok(approvals)
+}
+
+/// The Permit2 permit letting the Universal Router pull `swap`'s maximum input, for the sender to
+/// sign; `None` when the swap does not pull its input through Permit2. The nonce is read at the
+/// latest block, since a permit is only valid against the current state.
+pub async fn permit2_permit(
+    provider: &impl Provider<Ethereum>,
+    swap: &SimulatedSwap,
+) -> Result<Option<PermitSingle>> {
+    let Some(token) = swap.token_in.filter(|_| swap.uses_permit2()) else {
+        return Ok(None);
+    };
+    let router = swap.response.router_address;
+    let allowance = UniswapPermit2::new(PERMIT2, provider)
+        .allowance(swap.sender, token, router)
+        .call()
+        .await
+        .context("Failed to fetch Permit2 nonce")?;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    Ok(Some(permit_single(
+        token,
+        swap.max_input,
+        now + PERMIT_EXPIRATION_SECS,
+        allowance.nonce,
+        router,
+        swap_deadline()?,
+    )))
+}
+
+/// Check a permit signed elsewhere for `swap` and return it with its signature: it must be signed
+/// by the sender, for the swap's input and router, cover its maximum input, use Permit2's current
+/// nonce and still be within its deadlines. A permit that passes can replace the Permit2 approval.
+pub async fn check_signed_permit(
+    provider: &impl Provider<Ethereum>,
+    swap: &SimulatedSwap,
+    signed: &SignedPermit,
+) -> Result<(PermitSingle, Bytes)> {
+    let Some(token) = swap.token_in.filter(|_| swap.uses_permit2()) else {
+        bail!("Only Universal Router swaps of an ERC20 input take a signed permit");
+    };
+    let permit = permit_from_message(&signed.message)?;
+    let signature = Bytes::from_str(&signed.signature).context("Invalid permit signature hex")?;
+    let router = swap.response.router_address;
+    let details = &permit.details;
+    if details.token != token || permit.spender != router {
+        bail!("The signed permit is for token {} and spender {}, not {} and {}", details.token, permit.spender, token, router);
+    }
+    if U256::from(details.amount) < swap.max_input {
+        bail!("The signed permit allows {} but the swap may spend up to {}", details.amount, swap.max_input);
+    }
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    if permit.sigDeadline <= U256::from(now) || details.expiration.to::<u64>() <= now {
+        bail!("The signed permit has expired; sign a new one");
+    }
+    let signer = recover_permit_signer(swap.chain, &permit, &signature)?;
+    if signer != swap.sender {
+        bail!("The permit was signed by {}, not by the sender {}", signer, swap.sender);
+    }
+    let allowance = UniswapPermit2::new(PERMIT2, provider)
+        .allowance(swap.sender, token, router)
+        .call()
+        .await
+        .context("Failed to fetch Permit2 nonce")?;
+    if details.nonce != allowance.nonce {
+        bail!("The signed permit uses nonce {} but Permit2's current nonce is {}; sign a new one", details.nonce, allowance.nonce);
+    }
+    Ok((permit, signature))
+}
+
+fn erc20_approval(token: Address, spender: Address, amount: U256) -> Approval {
+    Approval {
+        kind: TransactionKind::Approve,
+        to: token,
+        data: IERC20::approveCall { spender, amount }.abi_encode().into(),
+    }
+}
+
+/// Build the approvals `swap` still needs and the swap itself as unsigned transactions, with
+/// consecutive nonces starting at the sender's pending nonce. With a `permit` checked by
+/// [`check_signed_permit`], the swap carries it and no Permit2 approval is sent.
+pub async fn build_transactions(
+    provider: &impl Provider<Ethereum>,
+    swap: &SimulatedSwap,
+    permit: Option<(PermitSingle, Bytes)>,
+) -> Result<Vec<UnsignedTransaction>> {
+    let sender = swap.sender;
+    let (nonce, approvals) = tokio::try_join!(
+        async {
+            provider
+                .get_transaction_count(sender)
+                .pending()
+                .await
+                .context(format!("Failed to fetch the nonce of {}", sender))
+        },
+        required_approvals(provider, swap, permit.is_some()),
+    )?;
+
+    let mut calls = Vec::new();
+    for approval in approvals {
+        let gas = match provider.estimate_gas(approval.request(sender)).await {
+            Ok(gas) => gas,
+            Err(e) => {
+                tracing::debug!("Failed to estimate {} gas, using {}: {}", approval.kind.name(), DEFAULT_APPROVAL_GAS, e);
+                DEFAULT_APPROVAL_GAS
+            }
+        };
+        calls.push((approval.kind, approval.to, approval.data, U256::ZERO, gas));
+    }
+    // A real estimate of the swap would fail until the approvals are mined, so the simulated one
+    // is used instead.
+    let simulation = &swap.response;
+    let (calldata, gas) = match permit {
+        Some((permit, signature)) => (
+            swap.calldata_with_permit(permit, signature)?,
+            simulation.gas_estimate + PERMIT_GAS,
+        ),
+        None => (simulation.calldata.clone(), simulation.gas_estimate),
+    };
+    calls.push((TransactionKind::Swap, simulation.router_address, calldata, simulation.value, gas));
+
+    Ok(calls
+        .into_iter()
+        .zip(nonce..)
+        .map(|((kind, to, data, value, gas), nonce)| UnsignedTransaction {
+            kind,
+            chain_id: swap.chain.chain_id,
+            from: sender,
+            to,
+            data,
+            value,
+            nonce,
+            gas_limit: buffered_gas_limit(gas),
+            max_fee_per_gas: max_fee_per_gas(swap.fees),
+            max_priority_fee_per_gas: swap.fees.priority_fee_per_gas,
+        })
+        .collect())
+}
+
+fn buffered_gas_limit(gas: u64) -> u64 {
+    gas.saturating_mul(100 + GAS_LIMIT_BUFFER_PERCENT) / 100
+}
+
+fn max_fee_per_gas(fees: FeeEstimate) -> u128 {
+    fees.base_fee_per_gas
+        .saturating_mul(2)
+        .saturating_add(fees.priority_fee_per_gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::MAINNET;
+
+    #[test]
+    fn test_fee_fields() {
+        assert_eq!(buffered_gas_limit(150_000), 180_000);
+        let fees = FeeEstimate {
+            base_fee_per_gas: 10_000_000_000,
+            priority_fee_per_gas: 1_000_000_000,
+        };
+        assert_eq!(max_fee_per_gas(fees), 21_000_000_000);
+    }
+
+    #[test]
+    fn test_erc20_approval_encodes_approve() {
+        let approval = erc20_approval(MAINNET.usd_token, PERMIT2, U256::from(1000));
+        assert_eq!(approval.to, MAINNET.usd_token);
+        let decoded = IERC20::approveCall::abi_decode(&approval.data).unwrap();
+        assert_eq!(decoded.spender, PERMIT2);
+        assert_eq!(decoded.amount, U256::from(1000));
+    }
+
+    #[test]
+    fn unsigned_transaction_serde() {
+        let tx = UnsignedTransaction {
+            kind: TransactionKind::Permit2Approve,
+            chain_id: 1,
+            from: Address::ZERO,
+            to: PERMIT2,
+            data: Bytes::from_static(&[0x87, 0x51, 0x7c, 0x45]),
+            value: U256::ZERO,
+            nonce: 7,
+            gas_limit: 60_000,
+            max_fee_per_gas: 21_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+        };
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["kind"], "permit2_approve");
+        assert_eq!(json["data"], "0x87517c45");
+        assert_eq!(json["nonce"], 7);
+        assert_eq!(json["max_fee_per_gas"], 21_000_000_000u64);
+    }
+}
@@ -0,0 +1,269 @@
+use alloy::primitives::Address;
+use alloy::signers::local::{MnemonicBuilder, PrivateKeySigner, coins_bip39::English};
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const ETH_PRIVATE_KEY: &str = "ETH_PRIVATE_KEY";
+
+/// Name of the account used when a tool call does not pick one.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Where an account's key comes from, as written in the accounts file. Secrets (private keys,
+/// mnemonics, passwords) are never written in the file itself, only the environment variable or
+/// file holding them.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AccountSource {
+    /// A raw hex private key.
+    PrivateKey {
+        private_key_env: Option<String>,
+        private_key_file: Option<PathBuf>,
+    },
+    /// An encrypted JSON keystore (Web3 Secret Storage), as written by geth, `cast wallet` or
+    /// MetaMask.
+    Keystore {
+        path: PathBuf,
+        password_env: Option<String>,
+        password_file: Option<PathBuf>,
+    },
+    /// A BIP-39 mnemonic, derived at `derivation_path` or at `m/44'/60'/0'/0/{index}`
+    /// (index 0 when neither is given).
+    Mnemonic {
+        mnemonic_env: Option<String>,
+        mnemonic_file: Option<PathBuf>,
+        derivation_path: Option<String>,
+        index: Option<u32>,
+    },
+}
+
+impl AccountSource {
+    /// Read the secrets and build the signer. Relative paths are resolved against `base`.
+    fn signer(&self, base: &Path) -> Result<PrivateKeySigner> {
+        match self {
+            AccountSource::PrivateKey { private_key_env, private_key_file } => {
+                let key = read_secret("private key", private_key_env, private_key_file, base)?;
+                key.parse().context("Invalid private key")
+            }
+            AccountSource::Keystore { path, password_env, password_file } => {
+                let password = read_secret("keystore password", password_env, password_file, base)?;
+                let path = base.join(path);
+                PrivateKeySigner::decrypt_keystore(&path, password)
+                    .context(format!("Failed to decrypt keystore {}", path.display()))
+            }
+            AccountSource::Mnemonic { mnemonic_env, mnemonic_file, derivation_path, index } => {
+                let phrase = read_secret("mnemonic", mnemonic_env, mnemonic_file, base)?;
+                let builder = MnemonicBuilder::<English>::default().phrase(phrase);
+                let builder = match (derivation_path, index) {
+                    (Some(_), Some(_)) => bail!("Give either derivation_path or index, not both"),
+                    (Some(path), None) => builder
+                        .derivation_path(path)
+                        .context(format!("Invalid derivation path: {}", path))?,
+                    (None, index) => builder.index(index.unwrap_or_default())?,
+                };
+                builder.build().context("Invalid mnemonic")
+            }
+        }
+    }
+}
+
+/// Read a secret from exactly one of an environment variable or a file, trimming whitespace.
+fn read_secret(
+    what: &str,
+    env: &Option<String>,
+    file: &Option<PathBuf>,
+    base: &Path,
+) -> Result<String> {
+    let secret = match (env, file) {
+        (Some(env), None) => std::env::var(env).context(format!("{} is not set ({})", env, what))?,
+        (None, Some(file)) => {
+            let path = base.join(file);
+            std::fs::read_to_string(&path)
+                .context(format!("Failed to read {} from {}", what, path.display()))?
+        }
+        _ => bail!("Give the {} as exactly one of an environment variable or a file", what),
+    };
+    Ok(secret.trim().to_string())
+}
+
+/// Signers available to tools, by account name, loaded once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct Accounts {
+    signers: Arc<BTreeMap<String, PrivateKeySigner>>,
+}
+
+impl Accounts {
+    /// Load the accounts file, if any, and `ETH_PRIVATE_KEY` as the 'default' account. With no
+    /// account at all the server runs in read-only mode.
+    pub fn load(file: Option<&Path>) -> Result<Self> {
+        Self::load_with(file, std::env::var(ETH_PRIVATE_KEY).ok())
+    }
+
+    /// [`load`](Self::load) with the value of `ETH_PRIVATE_KEY` given, so tests need not set it.
+    pub fn load_with(file: Option<&Path>, private_key: Option<String>) -> Result<Self> {
+        let mut signers = BTreeMap::new();
+        if let Some(file) = file {
+            let json = std::fs::read_to_string(file)
+                .context(format!("Failed to read accounts file {}", file.display()))?;
+            let sources: BTreeMap<String, AccountSource> = serde_json::from_str(&json)
+                .context(format!("Invalid accounts file {}", file.display()))?;
+            let base = file.parent().unwrap_or(Path::new("."));
+            for (name, source) in sources {
+                let signer = source.signer(base).context(format!("Failed to load account '{}'", name))?;
+                signers.insert(name, signer);
+            }
+        }
+
+        if let Some(private_key) = private_key {
+            if signers.contains_key(DEFAULT_ACCOUNT) {
+                bail!("{} and the accounts file both define the '{}' account", ETH_PRIVATE_KEY, DEFAULT_ACCOUNT);
+            }
+            let signer = private_key.parse().context(format!("Invalid {}", ETH_PRIVATE_KEY))?;
+            signers.insert(DEFAULT_ACCOUNT.to_string(), signer);
+        }
+        Ok(Self {
+            signers: Arc::new(signers),
+        })
+    }
+
+    /// Account names with their addresses, for logging.
+    pub fn addresses(&self) -> impl Iterator<Item = (&str, Address)> {
+        self.signers.iter().map(|(name, signer)| (name.as_str(), signer.address()))
+    }
+
+    /// The address of `account`; without a name, the 'default' account, or the only account when
+    /// exactly one is configured. `None` when no account applies.
+    pub fn get(&self, account: Option<&str>) -> Result<Option<Address>> {
+        let signer = match account.map(str::trim).filter(|account| !account.is_empty()) {
+            Some(account) => match self.signers.get(account) {
+                Some(signer) => Some(signer),
+                None => bail!(
+                    "Unknown account '{}'; configured accounts: [{}]",
+                    account,
+                    self.signers.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            },
+            None if self.signers.len() == 1 => self.signers.values().next(),
+            None => self.signers.get(DEFAULT_ACCOUNT),
+        };
+        Ok(signer.map(PrivateKeySigner::address))
+    }
+
+    /// The signer of the account at `address`. Only the spending policy hands signers out, so
+    /// nothing is signed without passing it.
+    pub(super) fn signer(&self, address: Address) -> Option<&PrivateKeySigner> {
+        self.signers.values().find(|signer| signer.address() == address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    // The standard test mnemonic of Hardhat and anvil, and the addresses of its first two keys.
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+    const FIRST: Address = address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const SECOND: Address = address!("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+
+    fn accounts(signers: Vec<(&str, PrivateKeySigner)>) -> Accounts {
+        Accounts {
+            signers: Arc::new(signers.into_iter().map(|(name, s)| (name.to_string(), s)).collect()),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("eth_mcp_server_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_mnemonic_accounts_by_index_or_path() {
+        let dir = temp_dir("accounts_mnemonic");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("mnemonic.txt"), format!("{}\n", TEST_MNEMONIC)).unwrap();
+
+        let by_index = AccountSource::Mnemonic {
+            mnemonic_env: None,
+            mnemonic_file: Some(PathBuf::from("mnemonic.txt")),
+            derivation_path: None,
+            index: Some(1),
+        };
+        assert_eq!(by_index.signer(&dir).unwrap().address(), SECOND);
+
+        let by_path: AccountSource = serde_json::from_str(
+            r#"{ "type": "mnemonic", "mnemonic_file": "mnemonic.txt", "derivation_path": "m/44'/60'/0'/0/0" }"#,
+        )
+        .unwrap();
+        assert_eq!(by_path.signer(&dir).unwrap().address(), FIRST);
+
+        let both = AccountSource::Mnemonic {
+            mnemonic_env: None,
+            mnemonic_file: Some(PathBuf::from("mnemonic.txt")),
+            derivation_path: Some("m/44'/60'/0'/0/0".to_string()),
+            index: Some(0),
+        };
+        assert!(both.signer(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keystore_account() {
+        let dir = temp_dir("accounts_keystore");
+        std::fs::create_dir_all(&dir).unwrap();
+        let (signer, _) = PrivateKeySigner::encrypt_keystore(
+            &dir,
+            &mut rand::thread_rng(),
+            PrivateKeySigner::random().to_bytes(),
+            "hunter2",
+            Some("trading.json"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("password.txt"), "hunter2\n").unwrap();
+        std::fs::write(
+            dir.join("accounts.json"),
+            r#"{ "trading": { "type": "keystore", "path": "trading.json", "password_file": "password.txt" } }"#,
+        )
+        .unwrap();
+
+        let accounts = Accounts::load_with(Some(&dir.join("accounts.json")), None).unwrap();
+        assert_eq!(accounts.get(Some("trading")).unwrap(), Some(signer.address()));
+        assert_eq!(accounts.signer(signer.address()).unwrap().address(), signer.address());
+
+        std::fs::write(dir.join("password.txt"), "wrong").unwrap();
+        assert!(Accounts::load_with(Some(&dir.join("accounts.json")), None).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_secrets_need_exactly_one_source() {
+        let source: AccountSource = serde_json::from_str(r#"{ "type": "private_key" }"#).unwrap();
+        assert!(source.signer(Path::new(".")).is_err());
+        let inline = r#"{ "type": "private_key", "private_key": "0x01" }"#;
+        assert!(serde_json::from_str::<AccountSource>(inline).is_err());
+    }
+
+    #[test]
+    fn test_select_account_by_name_or_default() {
+        let (first, second) = (PrivateKeySigner::random(), PrivateKeySigner::random());
+        let single = accounts(vec![("trading", first.clone())]);
+        assert_eq!(single.get(None).unwrap(), Some(first.address()));
+
+        let several = accounts(vec![("trading", first.clone()), ("ops", second.clone())]);
+        assert_eq!(several.get(None).unwrap(), None);
+        assert_eq!(several.get(Some("ops")).unwrap(), Some(second.address()));
+        assert!(several.get(Some("cold")).unwrap_err().to_string().contains("ops, trading"));
+
+        let with_default = accounts(vec![("trading", first), (DEFAULT_ACCOUNT, second.clone())]);
+        assert_eq!(with_default.get(None).unwrap(), Some(second.address()));
+        assert!(Accounts::default().get(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_private_key_env() {
+        let result = Accounts::load_with(None, Some("invalid-key".to_string()));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use alloy::eips::{BlockId, BlockNumberOrTag, RpcBlockHash};
+use alloy::network::Ethereum;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::future::Future;
+use std::str::FromStr;
+
+/// A block given as a number, hash, tag or timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSpec {
+    Id(BlockId),
+    /// Seconds since the Unix epoch; resolves to the last block at or before it.
+    Timestamp(u64),
+}
+
+impl FromStr for BlockSpec {
+    type Err = anyhow::Error;
+
+    /// Parse a decimal or hex block number, a block hash, a tag ('latest', 'finalized', ...) or an
+    /// ISO 8601 timestamp; a timestamp without a time zone, or a bare date, is read as UTC.
+    fn from_str(block: &str) -> Result<Self> {
+        let block = block.trim();
+        if let Ok(number) = block.parse::<u64>() {
+            return Ok(Self::Id(BlockId::number(number)));
+        }
+        if let Some(hex) = block.strip_prefix("0x") {
+            if hex.len() == 64 {
+                let hash = B256::from_str(block).context(format!("Invalid block hash: {}", block))?;
+                return Ok(Self::Id(BlockId::hash(hash)));
+            }
+            let number = u64::from_str_radix(hex, 16)
+                .context(format!("Invalid block number: {}", block))?;
+            return Ok(Self::Id(BlockId::number(number)));
+        }
+        if let Ok(tag) = BlockNumberOrTag::from_str(&block.to_lowercase()) {
+            return Ok(Self::Id(BlockId::Number(tag)));
+        }
+
+        let time = DateTime::parse_from_rfc3339(block)
+            .map(|time| time.with_timezone(&Utc))
+            .or_else(|_| NaiveDateTime::from_str(block).map(|time| time.and_utc()))
+            .or_else(|_| {
+                NaiveDate::from_str(block).map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+            })
+            .context(format!(
+                "Invalid block '{}': expected a number, hash, tag or ISO 8601 timestamp",
+                block
+            ))?;
+        let timestamp = u64::try_from(time.timestamp())
+            .context(format!("Timestamp {} is before the Unix epoch", block))?;
+        Ok(Self::Timestamp(timestamp))
+    }
+}
+
+/// A concrete block that every read of a request is pinned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PinnedBlock {
+    pub number: u64,
+    pub hash: B256,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl PinnedBlock {
+    /// Id reading the pinned block by hash, so a reorg fails the read instead of silently moving
+    /// it to another block at the same height.
+    pub fn id(&self) -> BlockId {
+        BlockId::Hash(RpcBlockHash {
+            block_hash: self.hash,
+            require_canonical: Some(true),
+        })
+    }
+}
+
+/// Block id to read state at: the pinned block, or `latest` when none was requested.
+pub fn block_id(block: Option<&PinnedBlock>) -> BlockId {
+    block.map_or(BlockId::latest(), PinnedBlock::id)
+}
+
+/// Resolve an optional `block` argument to a concrete block; `None` means the latest state.
+/// Tags are resolved once, so a request reads one consistent block.
+pub async fn pin_block(
+    provider: &impl Provider<Ethereum>,
+    block: Option<&str>,
+) -> Result<Option<PinnedBlock>> {
+    let Some(block) = block.map(str::trim).filter(|block| !block.is_empty()) else {
+        return Ok(None);
+    };
+    let pinned = match BlockSpec::from_str(block)? {
+        BlockSpec::Id(id) => fetch_block(provider, id).await?,
+        BlockSpec::Timestamp(timestamp) => block_at_timestamp(provider, timestamp).await?,
+    };
+    tracing::debug!("Pinned block '{}' to {} (timestamp {})", block, pinned.number, pinned.timestamp);
+    Ok(Some(pinned))
+}
+
+async fn fetch_block(provider: &impl Provider<Ethereum>, id: BlockId) -> Result<PinnedBlock> {
+    let block = provider
+        .get_block(id)
+        .await
+        .context(format!("Failed to fetch block {}", id))?
+        .context(format!("Block {} not found", id))?;
+    Ok(PinnedBlock {
+        number: block.header.number,
+        hash: block.header.hash,
+        timestamp: block.header.timestamp,
+    })
+}
+
+/// The last block at or before `timestamp`, by binary search over block timestamps.
+async fn block_at_timestamp(
+    provider: &impl Provider<Ethereum>,
+    timestamp: u64,
+) -> Result<PinnedBlock> {
+    let now = Utc::now().timestamp().max(0) as u64;
+    if timestamp > now {
+        bail!("Timestamp {} is in the future", timestamp);
+    }
+    let latest = fetch_block(provider, BlockId::latest()).await?;
+    let genesis = fetch_block(provider, BlockId::number(0)).await?;
+    search_timestamp(genesis, latest, timestamp, |number| {
+        fetch_block(provider, BlockId::number(number))
+    })
+    .await
+}
+
+/// Binary search between `first` and `last` for the last block at or before `timestamp`,
+/// fetching blocks by number with `fetch`.
+async fn search_timestamp<F, Fut>(
+    first: PinnedBlock,
+    last: PinnedBlock,
+    timestamp: u64,
+    fetch: F,
+) -> Result<PinnedBlock>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<PinnedBlock>>,
+{
+    if timestamp < first.timestamp {
+        bail!("Timestamp {} is before block {}", timestamp, first.number);
+    }
+    if timestamp >= last.timestamp {
+        return Ok(last);
+    }
+
+    // Invariant: low.timestamp <= timestamp < high.timestamp.
+    let (mut low, mut high) = (first, last);
+    while high.number - low.number > 1 {
+        let block = fetch(low.number + (high.number - low.number) / 2).await?;
+        tracing::trace!("Block {} has timestamp {}", block.number, block.timestamp);
+        if block.timestamp <= timestamp {
+            low = block;
+        } else {
+            high = block;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    // Twelve-second blocks starting at timestamp 1000.
+    fn block(number: u64) -> PinnedBlock {
+        PinnedBlock {
+            number,
+            hash: B256::with_last_byte(number as u8),
+            timestamp: 1000 + number * 12,
+        }
+    }
+
+    async fn search(timestamp: u64) -> Result<u64> {
+        let found = search_timestamp(block(0), block(100), timestamp, |number| async move {
+            Ok(block(number))
+        })
+        .await?;
+        Ok(found.number)
+    }
+
+    #[test]
+    fn test_parse_block_numbers_hashes_and_tags() {
+        let number = BlockSpec::Id(BlockId::number(19_000_000));
+        assert_eq!(BlockSpec::from_str("19000000").unwrap(), number);
+        assert_eq!(BlockSpec::from_str("0x1b4").unwrap(), BlockSpec::Id(BlockId::number(436)));
+        assert_eq!(
+            BlockSpec::from_str("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6")
+                .unwrap(),
+            BlockSpec::Id(BlockId::hash(b256!(
+                "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6"
+            )))
+        );
+        let finalized = BlockSpec::Id(BlockId::finalized());
+        assert_eq!(BlockSpec::from_str("Finalized").unwrap(), finalized);
+        assert!(BlockSpec::from_str("0xzz").is_err());
+        assert!(BlockSpec::from_str("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_block_timestamps() {
+        let expected = BlockSpec::Timestamp(1_704_067_200);
+        assert_eq!(BlockSpec::from_str("2024-01-01T00:00:00Z").unwrap(), expected);
+        assert_eq!(BlockSpec::from_str("2024-01-01T01:00:00+01:00").unwrap(), expected);
+        assert_eq!(BlockSpec::from_str("2024-01-01T00:00:00").unwrap(), expected);
+        assert_eq!(BlockSpec::from_str("2024-01-01").unwrap(), expected);
+        assert!(BlockSpec::from_str("1969-12-31").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_timestamp_finds_last_block_at_or_before() {
+        assert_eq!(search(1000).await.unwrap(), 0);
+        assert_eq!(search(1011).await.unwrap(), 0);
+        assert_eq!(search(1012).await.unwrap(), 1);
+        assert_eq!(search(1000 + 50 * 12 + 5).await.unwrap(), 50);
+        assert_eq!(search(1000 + 99 * 12 + 11).await.unwrap(), 99);
+        assert_eq!(search(5000).await.unwrap(), 100);
+        assert!(search(999).await.is_err());
+    }
+
+    #[test]
+    fn test_block_id_defaults_to_latest() {
+        assert_eq!(block_id(None), BlockId::latest());
+        assert_eq!(
+            block_id(Some(&block(7))),
+            BlockId::Hash(RpcBlockHash {
+                block_hash: B256::with_last_byte(7),
+                require_canonical: Some(true),
+            })
+        );
+    }
+}
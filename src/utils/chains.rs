@@ -0,0 +1,363 @@
+use alloy::primitives::{Address, address};
+use anyhow::{Result, bail};
+
+/// Which Uniswap V3 quoter contract is deployed on a chain.
+/// The original `Quoter` only exists on the early deployments; newer chains only ship `QuoterV2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoterVersion {
+    V1,
+    V2,
+}
+
+/// Permit2 is deployed at the same address on every chain.
+pub const PERMIT2: Address = address!("0x000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// Multicall3 is deployed at the same address on every chain.
+pub const MULTICALL3: Address = address!("0xcA11bde05977b3631167028862bE2a173976CA11");
+
+/// The ENS registry, deployed at the same address on Ethereum mainnet and Sepolia.
+pub const ENS_REGISTRY: Address = address!("0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+/// A Uniswap V2 style router (Uniswap V2 itself or a fork such as SushiSwap).
+#[derive(Debug, PartialEq, Eq)]
+pub struct V2Venue {
+    /// Name reported in tool responses (e.g., 'sushiswap').
+    pub name: &'static str,
+    pub router: Address,
+    /// Whether the Universal Router's V2 commands reach this venue's pairs (only Uniswap V2 itself).
+    pub universal_router: bool,
+}
+
+#[derive(Debug)]
+pub struct ChainProfile {
+    /// Canonical name used in tool arguments (e.g., 'arbitrum').
+    pub name: &'static str,
+    /// Alternative names accepted in tool arguments.
+    pub aliases: &'static [&'static str],
+    pub chain_id: u64,
+    /// Environment variable holding the RPC URL for this chain.
+    pub rpc_url_env: &'static str,
+    /// RPC URL used when `rpc_url_env` is not set.
+    pub default_rpc_url: Option<&'static str>,
+    pub native_symbol: &'static str,
+    /// Wrapped native asset (WETH, or WPOL on Polygon).
+    pub weth: Address,
+    /// USD stablecoin (USDC) used to value amounts in USD.
+    pub usd_token: Address,
+    pub v3_factory: Address,
+    pub v3_quoter: Address,
+    pub quoter_version: QuoterVersion,
+    /// Uniswap `SwapRouter02`.
+    pub v3_router: Address,
+    /// Uniswap `UniversalRouter`, used together with Permit2.
+    pub universal_router: Address,
+    /// Intermediate tokens tried for multi-hop routes (WETH, USDC, USDT, DAI, WBTC where deployed).
+    pub route_tokens: &'static [Address],
+    /// V2 style venues compared against Uniswap V3.
+    pub v2_venues: &'static [V2Venue],
+    /// ENS registry on this chain; other chains resolve ENS names on Ethereum mainnet.
+    pub ens_registry: Option<Address>,
+    /// Rollup that charges an L1 data fee on top of L2 execution gas. Gas costs reported by the
+    /// tools cover execution gas only, and flag it on these chains.
+    pub l1_data_fee: bool,
+}
+
+pub const MAINNET: ChainProfile = ChainProfile {
+    name: "ethereum",
+    aliases: &["mainnet", "eth"],
+    chain_id: 1,
+    rpc_url_env: "ETH_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "ETH",
+    weth: address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    usd_token: address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+    v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+    v3_quoter: address!("0xb27308f9F90D607463bb33ea1BeBb41C27CE5AB6"),
+    quoter_version: QuoterVersion::V1,
+    v3_router: address!("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+    universal_router: address!("0x66a9893cC07D91D95644AEDD05D03f95e1dBA8Af"),
+    route_tokens: &[
+        address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"), // WETH
+        address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), // USDC
+        address!("0xdAC17F958D2ee523a2206206994597C13D831ec7"), // USDT
+        address!("0x6B175474E89094C44Da98b954EedeAC495271d0F"), // DAI
+        address!("0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599"), // WBTC
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+            universal_router: true,
+        },
+        V2Venue {
+            name: "sushiswap",
+            router: address!("0xd9e1cE17f2641f24aE83637ab66a2cca9C378B9F"),
+            universal_router: false,
+        },
+    ],
+    ens_registry: Some(ENS_REGISTRY),
+    l1_data_fee: false,
+};
+
+pub const ARBITRUM: ChainProfile = ChainProfile {
+    name: "arbitrum",
+    aliases: &["arbitrum-one", "arb"],
+    chain_id: 42161,
+    rpc_url_env: "ARBITRUM_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "ETH",
+    weth: address!("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+    usd_token: address!("0xaf88d065e77c8cC2239327C5EDb3A432268e5831"),
+    v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+    v3_quoter: address!("0xb27308f9F90D607463bb33ea1BeBb41C27CE5AB6"),
+    quoter_version: QuoterVersion::V1,
+    v3_router: address!("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+    universal_router: address!("0xA51afAFe0263b40EdaEf0Df8781eA9aa03E381a3"),
+    route_tokens: &[
+        address!("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"), // WETH
+        address!("0xaf88d065e77c8cC2239327C5EDb3A432268e5831"), // USDC
+        address!("0xFd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9"), // USDT
+        address!("0xDA10009cBd5D07dd0CeCc66161FC93D7c9000da1"), // DAI
+        address!("0x2f2a2543B76A4166549F7aaB2e75Bef0aefC5B0f"), // WBTC
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24"),
+            universal_router: true,
+        },
+        V2Venue {
+            name: "sushiswap",
+            router: address!("0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506"),
+            universal_router: false,
+        },
+    ],
+    ens_registry: None,
+    l1_data_fee: true,
+};
+
+pub const OPTIMISM: ChainProfile = ChainProfile {
+    name: "optimism",
+    aliases: &["op"],
+    chain_id: 10,
+    rpc_url_env: "OPTIMISM_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "ETH",
+    weth: address!("0x4200000000000000000000000000000000000006"),
+    usd_token: address!("0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85"),
+    v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+    v3_quoter: address!("0xb27308f9F90D607463bb33ea1BeBb41C27CE5AB6"),
+    quoter_version: QuoterVersion::V1,
+    v3_router: address!("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+    universal_router: address!("0x851116D9223fabED8E56C0E6b8Ad0c31d98B3507"),
+    route_tokens: &[
+        address!("0x4200000000000000000000000000000000000006"), // WETH
+        address!("0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85"), // USDC
+        address!("0x94b008aA00579c1307B0EF2c499aD98a8ce58e58"), // USDT
+        address!("0xDA10009cBd5D07dd0CeCc66161FC93D7c9000da1"), // DAI
+        address!("0x68f180fcCe6836688e9084f035309E29Bf0A2095"), // WBTC
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0x4A7b5Da61326A6379179b40d00F57E5bbDC962c2"),
+            universal_router: true,
+        },
+    ],
+    ens_registry: None,
+    l1_data_fee: true,
+};
+
+pub const BASE: ChainProfile = ChainProfile {
+    name: "base",
+    aliases: &[],
+    chain_id: 8453,
+    rpc_url_env: "BASE_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "ETH",
+    weth: address!("0x4200000000000000000000000000000000000006"),
+    usd_token: address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+    v3_factory: address!("0x33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+    v3_quoter: address!("0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a"),
+    quoter_version: QuoterVersion::V2,
+    v3_router: address!("0x2626664c2603336E57B271c5C0b26F421741e481"),
+    universal_router: address!("0x6fF5693b99212Da76ad316178A184AB56D299b43"),
+    route_tokens: &[
+        address!("0x4200000000000000000000000000000000000006"), // WETH
+        address!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"), // USDC
+        address!("0x50c5725949A6F0c72E6C4a641F24049A917DB0Cb"), // DAI
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0x4752ba5DBc23f44D87826276BF6Fd6b1C372aD24"),
+            universal_router: true,
+        },
+    ],
+    ens_registry: None,
+    l1_data_fee: true,
+};
+
+pub const POLYGON: ChainProfile = ChainProfile {
+    name: "polygon",
+    aliases: &["matic", "pol"],
+    chain_id: 137,
+    rpc_url_env: "POLYGON_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "POL",
+    weth: address!("0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"),
+    usd_token: address!("0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359"),
+    v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+    v3_quoter: address!("0xb27308f9F90D607463bb33ea1BeBb41C27CE5AB6"),
+    quoter_version: QuoterVersion::V1,
+    v3_router: address!("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+    universal_router: address!("0x1095692A6237d83C6a72F3F5eFEdb9A670C49223"),
+    route_tokens: &[
+        address!("0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"), // WPOL
+        address!("0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619"), // WETH
+        address!("0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359"), // USDC
+        address!("0xc2132D05D31c914a87C6611C10748AEb04B58e8F"), // USDT
+        address!("0x8f3Cf7ad23Cd3CaDbD9735AFf958023239c6A063"), // DAI
+        address!("0x1BFD67037B42Cf73acF2047067bd4F2C47D9BfD6"), // WBTC
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0xedf6066a2b290C185783862C7F4776A2C8077AD1"),
+            universal_router: true,
+        },
+        V2Venue {
+            name: "sushiswap",
+            router: address!("0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506"),
+            universal_router: false,
+        },
+    ],
+    ens_registry: None,
+    l1_data_fee: false,
+};
+
+pub const SEPOLIA: ChainProfile = ChainProfile {
+    name: "sepolia",
+    aliases: &[],
+    chain_id: 11155111,
+    rpc_url_env: "SEPOLIA_RPC_URL",
+    default_rpc_url: None,
+    native_symbol: "ETH",
+    weth: address!("0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14"),
+    usd_token: address!("0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"),
+    v3_factory: address!("0x0227628f3F023bb0B980b67D528571c95c6DaC1c"),
+    v3_quoter: address!("0xEd1f6473345F45b75F8179591dd5bA1888cf2FB3"),
+    quoter_version: QuoterVersion::V2,
+    v3_router: address!("0x3bFA4769FB09eefC5a80d6E87c3B9C650f7Ae48E"),
+    universal_router: address!("0x3A9D48AB9751398BbFa63ad67599Bb04e4BdF98b"),
+    route_tokens: &[
+        address!("0xfFf9976782d46CC05630D1f6eBAb18b2324d6B14"), // WETH
+        address!("0x1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"), // USDC
+    ],
+    v2_venues: &[
+        V2Venue {
+            name: "uniswap_v2",
+            router: address!("0xeE567Fe1712Faf6149d80dA1E6934E354124CfE3"),
+            universal_router: true,
+        },
+    ],
+    ens_registry: Some(ENS_REGISTRY),
+    l1_data_fee: false,
+};
+
+/// Local dev chain (anvil / hardhat), assumed to be a fork of Ethereum mainnet.
+pub const LOCAL: ChainProfile = ChainProfile {
+    name: "local",
+    aliases: &["anvil", "hardhat", "localhost", "dev"],
+    chain_id: 31337,
+    rpc_url_env: "LOCAL_RPC_URL",
+    default_rpc_url: Some("http://127.0.0.1:8545"),
+    ..MAINNET
+};
+
+pub const CHAIN_PROFILES: [&ChainProfile; 7] =
+    [&MAINNET, &ARBITRUM, &OPTIMISM, &BASE, &POLYGON, &SEPOLIA, &LOCAL];
+
+pub const DEFAULT_CHAIN: &ChainProfile = &MAINNET;
+
+impl ChainProfile {
+    /// Whether `token` names the chain's native asset (e.g., 'ETH') rather than an ERC20.
+    pub fn is_native(&self, token: &str) -> bool {
+        token.trim().eq_ignore_ascii_case(self.native_symbol)
+    }
+}
+
+/// Resolve a chain by name, alias or numeric chain id; `None` selects Ethereum mainnet.
+pub fn resolve_chain(chain: Option<&str>) -> Result<&'static ChainProfile> {
+    let Some(chain) = chain.map(str::trim).filter(|c| !c.is_empty()) else {
+        return Ok(DEFAULT_CHAIN);
+    };
+
+    if let Ok(chain_id) = chain.parse::<u64>() {
+        if let Some(profile) = CHAIN_PROFILES.into_iter().find(|p| p.chain_id == chain_id) {
+            return Ok(profile);
+        }
+        bail!("Unsupported chain id: {}", chain_id);
+    }
+
+    let chain_lower = chain.to_lowercase();
+    match CHAIN_PROFILES
+        .into_iter()
+        .find(|p| p.name == chain_lower || p.aliases.contains(&chain_lower.as_str()))
+    {
+        Some(profile) => Ok(profile),
+        None => bail!(
+            "Unsupported chain '{}'; supported chains: {}",
+            chain,
+            CHAIN_PROFILES.map(|p| p.name).join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_chain_defaults_to_mainnet() {
+        assert_eq!(resolve_chain(None).unwrap().chain_id, 1);
+        assert_eq!(resolve_chain(Some("")).unwrap().chain_id, 1);
+    }
+
+    #[test]
+    fn test_resolve_chain_by_name_and_alias() {
+        assert_eq!(resolve_chain(Some("arbitrum")).unwrap().chain_id, 42161);
+        assert_eq!(resolve_chain(Some("ARB")).unwrap().chain_id, 42161);
+        assert_eq!(resolve_chain(Some("anvil")).unwrap().chain_id, 31337);
+    }
+
+    #[test]
+    fn test_resolve_chain_by_id() {
+        assert_eq!(resolve_chain(Some("8453")).unwrap().name, "base");
+        assert!(resolve_chain(Some("999999")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_chain_unknown_name() {
+        let err = resolve_chain(Some("solana")).unwrap_err().to_string();
+        assert!(err.contains("Unsupported chain"));
+        assert!(err.contains("ethereum"));
+    }
+
+    #[test]
+    fn test_is_native() {
+        assert!(MAINNET.is_native("ETH"));
+        assert!(MAINNET.is_native("eth"));
+        assert!(!MAINNET.is_native("WETH"));
+        assert!(POLYGON.is_native("POL"));
+        assert!(!POLYGON.is_native("ETH"));
+    }
+
+    #[test]
+    fn test_chain_profiles_have_unique_ids() {
+        let mut ids: Vec<u64> = CHAIN_PROFILES.iter().map(|p| p.chain_id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), CHAIN_PROFILES.len());
+    }
+}
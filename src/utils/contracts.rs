@@ -29,3 +29,62 @@ sol!(
     UniswapPermit2,
     "abi/UniswapPermit2.json"
 );
+
+sol!(
+    #[sol(rpc)]
+    UniswapV3QuoterV2,
+    "abi/UniswapV3QuoterV2.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    UniswapV3Factory,
+    "abi/UniswapV3Factory.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    UniswapV3Pool,
+    "abi/UniswapV3Pool.json"
+);
+
+// The V2 router ABI includes liquidity functions with many parameters.
+sol!(
+    #[allow(clippy::too_many_arguments)]
+    #[sol(rpc)]
+    UniswapV2Router,
+    "abi/UniswapV2Router.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    UniswapV2Factory,
+    "abi/UniswapV2Factory.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    UniswapV2Pair,
+    "abi/UniswapV2Pair.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    ENSRegistry,
+    "abi/ENSRegistry.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    ENSResolver,
+    "abi/ENSResolver.json"
+);
+
+sol!(
+    #[sol(rpc)]
+    Multicall3,
+    "abi/Multicall3.json"
+);
+
+// Only the events, to follow native (un)wrapping in transaction receipts.
+sol!(WETH9, "abi/WETH9.json");
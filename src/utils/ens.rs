@@ -0,0 +1,269 @@
+use alloy::eips::BlockId;
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, B256, keccak256};
+use alloy::providers::Provider;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+
+use crate::utils::chains::{ChainProfile, MAINNET};
+use crate::utils::contracts::{ENSRegistry, ENSResolver};
+use crate::utils::provider::Providers;
+use crate::utils::token_registry::resolve_token;
+
+// Lookups are cached per chain until a new block is seen.
+static FORWARD_CACHE: LazyLock<BlockCache<(u64, String), Option<Address>>> =
+    LazyLock::new(Default::default);
+static REVERSE_CACHE: LazyLock<BlockCache<(u64, Address), Option<String>>> =
+    LazyLock::new(Default::default);
+
+/// Results valid for the block they were read at.
+struct BlockCache<K, V> {
+    entries: Mutex<HashMap<K, (u64, V)>>,
+}
+
+impl<K, V> Default for BlockCache<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> BlockCache<K, V> {
+    fn get(&self, key: &K, block: u64) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(cached_block, _)| *cached_block == block)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn insert(&self, key: K, block: u64, value: V) {
+        self.entries.lock().unwrap().insert(key, (block, value));
+    }
+}
+
+/// Whether `input` looks like an ENS name (e.g., 'vitalik.eth') rather than a hex address or symbol.
+pub fn is_ens_name(input: &str) -> bool {
+    let input = input.trim();
+    input.len() > ".eth".len()
+        && input.to_lowercase().ends_with(".eth")
+        && !input.contains(char::is_whitespace)
+}
+
+/// ENS namehash (EIP-137) of a name. Labels are lowercased; full ENSIP-15 normalization is not applied.
+pub fn namehash(name: &str) -> B256 {
+    let name = name.trim().to_lowercase();
+    name.rsplit('.')
+        .filter(|label| !label.is_empty())
+        .fold(B256::ZERO, |node, label| {
+            keccak256([node.as_slice(), keccak256(label).as_slice()].concat())
+        })
+}
+
+/// Name of the reverse record of `address` (`<hex address>.addr.reverse`).
+fn reverse_name(address: Address) -> String {
+    format!("{:x}.addr.reverse", address)
+}
+
+/// Provider and registry used for ENS lookups on `chain`: its own registry, or Ethereum mainnet's.
+fn ens_provider<'a>(
+    providers: &'a Providers,
+    chain: &'static ChainProfile,
+) -> Result<(&'static ChainProfile, &'a alloy::providers::DynProvider, Address)> {
+    let ens_chain = if chain.ens_registry.is_some() { chain } else { &MAINNET };
+    let registry = ens_chain.ens_registry.context("ENS registry is not configured")?;
+    let provider = providers
+        .get(ens_chain)
+        .context(format!("ENS names resolve on {}", ens_chain.name))?;
+    Ok((ens_chain, provider, registry))
+}
+
+/// Parse `input` as a hex address or resolve it as an ENS name.
+pub async fn resolve_address(
+    providers: &Providers,
+    chain: &'static ChainProfile,
+    input: &str,
+) -> Result<Address> {
+    if is_ens_name(input) {
+        return resolve_name(providers, chain, input).await;
+    }
+    Address::from_str(input.trim()).context(format!("Invalid address: {}", input))
+}
+
+/// Resolve a token given as a hex address, ENS name or listed symbol.
+pub async fn resolve_token_or_name(
+    providers: &Providers,
+    chain: &'static ChainProfile,
+    token: &str,
+) -> Result<Address> {
+    if is_ens_name(token) {
+        return resolve_name(providers, chain, token).await;
+    }
+    resolve_token(token, chain.chain_id).await
+}
+
+/// Resolve an ENS name to the address its resolver reports, failing if it has none.
+pub async fn resolve_name(
+    providers: &Providers,
+    chain: &'static ChainProfile,
+    name: &str,
+) -> Result<Address> {
+    let (ens_chain, provider, registry) = ens_provider(providers, chain)?;
+    lookup_name(provider, ens_chain.chain_id, registry, name)
+        .await?
+        .context(format!("ENS name '{}' does not resolve to an address", name))
+}
+
+async fn lookup_name(
+    provider: &impl Provider<Ethereum>,
+    chain_id: u64,
+    registry: Address,
+    name: &str,
+) -> Result<Option<Address>> {
+    let block = provider.get_block_number().await.context("Failed to fetch block number")?;
+    let key = (chain_id, name.trim().to_lowercase());
+    if let Some(address) = FORWARD_CACHE.get(&key, block) {
+        tracing::trace!("ENS name {} cached at block {}: {:?}", name, block, address);
+        return Ok(address);
+    }
+
+    tracing::debug!("Resolving ENS name {} at block {}", name, block);
+    let node = namehash(name);
+    let address = match resolver(provider, registry, node, block).await? {
+        Some(resolver) => {
+            let address = ENSResolver::new(resolver, provider)
+                .addr(node)
+                .block(BlockId::number(block))
+                .call()
+                .await
+                .context(format!("Failed to resolve ENS name '{}'", name))?;
+            (address != Address::ZERO).then_some(address)
+        }
+        None => None,
+    };
+    FORWARD_CACHE.insert(key, block, address);
+    Ok(address)
+}
+
+/// The primary ENS name of `address`, only if it resolves back to the same address.
+pub async fn reverse_resolve(
+    providers: &Providers,
+    chain: &'static ChainProfile,
+    address: Address,
+) -> Result<Option<String>> {
+    let (ens_chain, provider, registry) = ens_provider(providers, chain)?;
+    let block = provider.get_block_number().await.context("Failed to fetch block number")?;
+    let key = (ens_chain.chain_id, address);
+    if let Some(name) = REVERSE_CACHE.get(&key, block) {
+        tracing::trace!("ENS reverse record of {} cached at block {}: {:?}", address, block, name);
+        return Ok(name);
+    }
+
+    tracing::debug!("Reverse resolving {} at block {}", address, block);
+    let node = namehash(&reverse_name(address));
+    let name = match resolver(provider, registry, node, block).await? {
+        Some(resolver) => ENSResolver::new(resolver, provider)
+            .name(node)
+            .block(BlockId::number(block))
+            .call()
+            .await
+            .context(format!("Failed to read ENS reverse record of {}", address))?,
+        None => String::new(),
+    };
+
+    // Anyone can claim any name in their reverse record; only trust it if it points back.
+    let verified = if name.is_empty() {
+        None
+    } else if lookup_name(provider, ens_chain.chain_id, registry, &name).await? == Some(address) {
+        Some(name)
+    } else {
+        tracing::debug!("Reverse record of {} names {}, which does not resolve back", address, name);
+        None
+    };
+    REVERSE_CACHE.insert(key, block, verified.clone());
+    Ok(verified)
+}
+
+async fn resolver(
+    provider: &impl Provider<Ethereum>,
+    registry: Address,
+    node: B256,
+    block: u64,
+) -> Result<Option<Address>> {
+    let resolver = ENSRegistry::new(registry, provider)
+        .resolver(node)
+        .block(BlockId::number(block))
+        .call()
+        .await
+        .context("Failed to fetch ENS resolver")?;
+    Ok((resolver != Address::ZERO).then_some(resolver))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::ARBITRUM;
+    use alloy::primitives::{address, b256};
+
+    #[test]
+    fn test_namehash_matches_eip137() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("eth"),
+            b256!("0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+        );
+        assert_eq!(
+            namehash("foo.eth"),
+            b256!("0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+        );
+        assert_eq!(namehash("Foo.ETH"), namehash("foo.eth"));
+    }
+
+    #[test]
+    fn test_is_ens_name() {
+        assert!(is_ens_name("vitalik.eth"));
+        assert!(is_ens_name("Pay.Vitalik.ETH"));
+        assert!(!is_ens_name(".eth"));
+        assert!(!is_ens_name("WETH"));
+        assert!(!is_ens_name("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+        assert!(!is_ens_name("not a name.eth"));
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        let address = address!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+        assert_eq!(reverse_name(address), "d8da6bf26964af9d7eed9e03e53415d37aa96045.addr.reverse");
+    }
+
+    #[test]
+    fn test_block_cache_expires_on_new_block() {
+        let cache = BlockCache::<u64, &str>::default();
+        cache.insert(1, 100, "a");
+        assert_eq!(cache.get(&1, 100), Some("a"));
+        assert_eq!(cache.get(&1, 101), None);
+        assert_eq!(cache.get(&2, 100), None);
+    }
+
+    #[tokio::test]
+    async fn test_l2_names_resolve_on_mainnet() {
+        let err = resolve_name(&Providers::default(), &ARBITRUM, "vitalik.eth").await.unwrap_err();
+        assert!(err.to_string().contains("ENS names resolve on ethereum"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_address_parses_hex() {
+        let address = resolve_address(
+            &Providers::default(),
+            &MAINNET,
+            "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045",
+        )
+        .await
+        .unwrap();
+        assert_eq!(address, address!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"));
+        assert!(resolve_address(&Providers::default(), &MAINNET, "0x123").await.is_err());
+    }
+}
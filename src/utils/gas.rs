@@ -0,0 +1,148 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::Ethereum;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::rpc::types::FeeHistory;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+
+use crate::utils::decimals::u256_to_decimal;
+
+/// Every supported chain's native asset has 18 decimals.
+pub const NATIVE_DECIMALS: u8 = 18;
+const GWEI_DECIMALS: u8 = 9;
+
+/// Number of recent blocks sampled from `eth_feeHistory`.
+pub const FEE_HISTORY_BLOCKS: u64 = 20;
+/// Reward percentile used for the standard priority fee suggestion.
+pub const STANDARD_PRIORITY_PERCENTILE: f64 = 50.0;
+/// Reward percentiles for the slow, standard and fast priority fee suggestions.
+pub const PRIORITY_PERCENTILES: [f64; 3] = [10.0, STANDARD_PRIORITY_PERCENTILE, 90.0];
+
+/// EIP-1559 fees expected for a transaction included in the next block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// Base fee predicted for the next block, in wei.
+    pub base_fee_per_gas: u128,
+    /// Suggested priority fee (tip), in wei.
+    pub priority_fee_per_gas: u128,
+}
+
+impl FeeEstimate {
+    /// Effective price paid per gas unit: the base fee plus the tip.
+    pub fn gas_price(&self) -> u128 {
+        self.base_fee_per_gas.saturating_add(self.priority_fee_per_gas)
+    }
+
+    /// Cost in wei of `gas` units at this estimate.
+    pub fn cost(&self, gas: u64) -> U256 {
+        U256::from(gas) * U256::from(self.gas_price())
+    }
+}
+
+/// Estimate fees for the block after `newest_block` from `eth_feeHistory`: the predicted base fee
+/// and the median tip paid at the standard percentile over the blocks up to it.
+pub async fn estimate_fees(
+    provider: &impl Provider<Ethereum>,
+    newest_block: BlockNumberOrTag,
+) -> Result<FeeEstimate> {
+    let history = fee_history(provider, newest_block, &[STANDARD_PRIORITY_PERCENTILE]).await?;
+    fee_estimate(&history, 0)
+}
+
+/// Fetch `eth_feeHistory` for the `FEE_HISTORY_BLOCKS` blocks up to `newest_block` with the given
+/// reward percentiles.
+pub async fn fee_history(
+    provider: &impl Provider<Ethereum>,
+    newest_block: BlockNumberOrTag,
+    reward_percentiles: &[f64],
+) -> Result<FeeHistory> {
+    let history = provider
+        .get_fee_history(FEE_HISTORY_BLOCKS, newest_block, reward_percentiles)
+        .await
+        .context("Failed to fetch fee history")?;
+    tracing::trace!("Fee history from block {}: {:?}", history.oldest_block, history.base_fee_per_gas);
+    Ok(history)
+}
+
+/// Fee estimate using the reward percentile at `percentile_index` of the fee history request.
+pub fn fee_estimate(history: &FeeHistory, percentile_index: usize) -> Result<FeeEstimate> {
+    let base_fee_per_gas = history
+        .next_block_base_fee()
+        .context("Fee history has no base fee; the chain may not support EIP-1559")?;
+    Ok(FeeEstimate {
+        base_fee_per_gas,
+        priority_fee_per_gas: median(percentile_rewards(history, percentile_index)),
+    })
+}
+
+/// Each sampled block's reward at `percentile_index` of the requested percentiles.
+fn percentile_rewards(history: &FeeHistory, percentile_index: usize) -> Vec<u128> {
+    history
+        .reward
+        .iter()
+        .flatten()
+        .filter_map(|rewards| rewards.get(percentile_index).copied())
+        .collect()
+}
+
+/// Median of `values`, or zero when there are none.
+fn median(mut values: Vec<u128>) -> u128 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        values[mid - 1].midpoint(values[mid])
+    } else {
+        values[mid]
+    }
+}
+
+pub fn wei_to_gwei(wei: u128) -> Result<Decimal> {
+    u256_to_decimal(U256::from(wei), GWEI_DECIMALS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn history(base_fees: Vec<u128>, rewards: Vec<Vec<u128>>) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: base_fees,
+            reward: Some(rewards),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(vec![]), 0);
+        assert_eq!(median(vec![3, 1, 2]), 2);
+        assert_eq!(median(vec![4, 1, 3, 2]), 2);
+        assert_eq!(median(vec![u128::MAX, u128::MAX]), u128::MAX);
+    }
+
+    #[test]
+    fn test_fee_estimate_uses_next_base_fee_and_median_reward() {
+        let history = history(vec![10, 11, 12], vec![vec![1, 5], vec![3, 9]]);
+        let estimate = fee_estimate(&history, 1).unwrap();
+        assert_eq!(estimate.base_fee_per_gas, 12);
+        assert_eq!(estimate.priority_fee_per_gas, 7);
+        assert_eq!(estimate.gas_price(), 19);
+        assert_eq!(estimate.cost(100), U256::from(1900));
+    }
+
+    #[test]
+    fn test_fee_estimate_without_base_fee() {
+        let history = history(vec![], vec![]);
+        assert!(fee_estimate(&history, 0).is_err());
+    }
+
+    #[test]
+    fn test_wei_to_gwei() {
+        assert_eq!(wei_to_gwei(1_500_000_000).unwrap(), Decimal::from_str("1.5").unwrap());
+    }
+}
@@ -0,0 +1,256 @@
+use alloy::eips::BlockId;
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use anyhow::{Context, Result, bail};
+use rust_decimal::Decimal;
+use rust_decimal::dec;
+
+use crate::utils::chains::ChainProfile;
+use crate::utils::contracts::{
+    UniswapV2Factory, UniswapV2Pair, UniswapV2Router, UniswapV3Factory, UniswapV3Pool,
+};
+use crate::utils::multicall::{Call, aggregate, decode};
+use crate::utils::routing::{Route, Venue};
+
+// Mid prices are informational, so raw pool prices are combined as f64: a squared sqrtPriceX96
+// overflows U256 and raw prices span far more magnitudes than `Decimal` can hold.
+
+/// Price of one V3 pool from its Q64.96 square root price, as raw `token1` per raw `token0`.
+fn sqrt_price_x96_to_price(sqrt_price_x96: U256) -> f64 {
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96);
+    sqrt_price * sqrt_price
+}
+
+/// Mid price of a V3 pool hop, in raw `token_out` per raw `token_in`, excluding fees.
+fn v3_hop_mid_price(sqrt_price_x96: U256, token0: Address, token_in: Address) -> f64 {
+    let price = sqrt_price_x96_to_price(sqrt_price_x96);
+    if token_in == token0 { price } else { 1.0 / price }
+}
+
+/// Mid price of a V2 pair hop from its reserves, in raw `token_out` per raw `token_in`, excluding
+/// fees.
+fn v2_hop_mid_price(reserve0: U256, reserve1: U256, token0: Address, token_in: Address) -> f64 {
+    let (reserve_in, reserve_out) = if token_in == token0 {
+        (reserve0, reserve1)
+    } else {
+        (reserve1, reserve0)
+    };
+    f64::from(reserve_out) / f64::from(reserve_in)
+}
+
+/// The V3 pool or V2 pair behind every hop of `route`, looked up in one batch.
+async fn route_pools(
+    chain: &ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    route: &Route,
+) -> Result<Vec<Address>> {
+    let hops = route.tokens.windows(2).zip(&route.fees);
+    let calls: Vec<Call> = match route.venue {
+        Venue::UniswapV3 => hops
+            .map(|(pair, &fee)| {
+                let get_pool = UniswapV3Factory::getPoolCall {
+                    tokenA: pair[0],
+                    tokenB: pair[1],
+                    fee,
+                };
+                Call::new(chain.v3_factory, &get_pool)
+            })
+            .collect(),
+        Venue::V2(venue) => {
+            let factory = UniswapV2Router::new(venue.router, provider)
+                .factory()
+                .block(block)
+                .call()
+                .await
+                .context("Failed to fetch V2 factory")?;
+            hops.map(|(pair, _)| {
+                let get_pair = UniswapV2Factory::getPairCall {
+                    tokenA: pair[0],
+                    tokenB: pair[1],
+                };
+                Call::new(factory, &get_pair)
+            })
+            .collect()
+        }
+    };
+    let outputs = aggregate(provider, block, &calls)
+        .await
+        .context("Failed to fetch route pools")?;
+
+    route
+        .tokens
+        .windows(2)
+        .zip(&route.fees)
+        .zip(outputs)
+        .map(|((pair, fee), output)| {
+            // getPool and getPair both return a single address.
+            match decode::<UniswapV3Factory::getPoolCall>(output.as_ref()) {
+                Some(pool) if pool != Address::ZERO => Ok(pool),
+                _ => match route.venue {
+                    Venue::UniswapV3 => bail!("No V3 pool for {}/{} with fee {}", pair[0], pair[1], fee),
+                    Venue::V2(venue) => bail!("No {} pair for {}/{}", venue.name, pair[0], pair[1]),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Mid price of `route` in whole `token_out` per whole `token_in`: the product of every hop's
+/// spot price, before fees and price impact.
+pub async fn route_mid_price(
+    chain: &ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    route: &Route,
+    decimals_in: u8,
+    decimals_out: u8,
+) -> Result<Decimal> {
+    let pools = route_pools(chain, provider, block, route).await?;
+
+    // The price state and token0 of every pool, in one batch.
+    let calls: Vec<Call> = pools
+        .iter()
+        .flat_map(|&pool| match route.venue {
+            Venue::UniswapV3 => [
+                Call::new(pool, &UniswapV3Pool::slot0Call {}),
+                Call::new(pool, &UniswapV3Pool::token0Call {}),
+            ],
+            Venue::V2(_) => [
+                Call::new(pool, &UniswapV2Pair::getReservesCall {}),
+                Call::new(pool, &UniswapV2Pair::token0Call {}),
+            ],
+        })
+        .collect();
+    let outputs = aggregate(provider, block, &calls)
+        .await
+        .context("Failed to fetch pool state")?;
+
+    let mut raw_price = 1.0;
+    for ((pair, pool), output) in route.tokens.windows(2).zip(&pools).zip(outputs.chunks(2)) {
+        let (state, token0) = (output[0].as_ref(), output[1].as_ref());
+        raw_price *= match route.venue {
+            Venue::UniswapV3 => {
+                let slot0 = decode::<UniswapV3Pool::slot0Call>(state)
+                    .context(format!("Failed to fetch slot0 of V3 pool {}", pool))?;
+                let token0 = decode::<UniswapV3Pool::token0Call>(token0)
+                    .context(format!("Failed to fetch token0 of V3 pool {}", pool))?;
+                v3_hop_mid_price(U256::from(slot0.sqrtPriceX96), token0, pair[0])
+            }
+            Venue::V2(_) => {
+                let reserves = decode::<UniswapV2Pair::getReservesCall>(state)
+                    .context(format!("Failed to fetch reserves of V2 pair {}", pool))?;
+                let token0 = decode::<UniswapV2Pair::token0Call>(token0)
+                    .context(format!("Failed to fetch token0 of V2 pair {}", pool))?;
+                let (reserve0, reserve1) = (U256::from(reserves.reserve0), U256::from(reserves.reserve1));
+                v2_hop_mid_price(reserve0, reserve1, token0, pair[0])
+            }
+        };
+    }
+
+    let scale = 10f64.powi(i32::from(decimals_in) - i32::from(decimals_out));
+    to_decimal_price(raw_price * scale)
+}
+
+fn to_decimal_price(price: f64) -> Result<Decimal> {
+    Decimal::try_from(price)
+        .map(|price| price.normalize())
+        .context(format!("Mid price {} is out of range", price))
+}
+
+/// Execution price in whole `token_out` per whole `token_in`.
+pub fn execution_price(amount_in: Decimal, amount_out: Decimal) -> Result<Decimal> {
+    amount_out
+        .checked_div(amount_in)
+        .map(|price| price.normalize())
+        .context("Cannot compute execution price for a zero input amount")
+}
+
+/// How far the execution price falls short of the mid price after the route's `fee_percent`, in
+/// percent: the market impact of the trade alone, with fees reported separately.
+pub fn price_impact_percent(mid_price: Decimal, execution_price: Decimal, fee_percent: Decimal) -> Decimal {
+    let mid_after_fees = mid_price * (Decimal::ONE - fee_percent / dec!(100));
+    if mid_after_fees.is_zero() {
+        return Decimal::ZERO;
+    }
+    ((mid_after_fees - execution_price) / mid_after_fees * dec!(100))
+        .round_dp(4)
+        .normalize()
+}
+
+/// Total fee charged along a route, in percent, from each hop's fee in hundredths of a bip.
+pub fn route_fee_percent(fees: &[u32]) -> Decimal {
+    let kept = fees
+        .iter()
+        .fold(Decimal::ONE, |kept, &fee| kept * (Decimal::ONE - Decimal::new(fee.into(), 6)));
+    ((Decimal::ONE - kept) * dec!(100)).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sqrt_price_x96_to_price() {
+        // sqrtPriceX96 = 2^96 means a price of exactly 1.
+        assert_eq!(sqrt_price_x96_to_price(U256::from(1) << 96), 1.0);
+        // sqrtPriceX96 = 2^97 means a price of 4.
+        assert_eq!(sqrt_price_x96_to_price(U256::from(1) << 97), 4.0);
+    }
+
+    #[test]
+    fn test_hop_mid_price_follows_token_order() {
+        let (token_a, token_b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let sqrt_price = U256::from(1) << 97;
+        assert_eq!(v3_hop_mid_price(sqrt_price, token_a, token_a), 4.0);
+        assert_eq!(v3_hop_mid_price(sqrt_price, token_a, token_b), 0.25);
+
+        let (reserve0, reserve1) = (U256::from(1000), U256::from(3000));
+        assert_eq!(v2_hop_mid_price(reserve0, reserve1, token_a, token_a), 3.0);
+        assert_eq!(v2_hop_mid_price(reserve0, reserve1, token_a, token_b), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_to_decimal_price_scales_to_whole_units() {
+        // 1 WETH (18 decimals) = 3000 USDC (6 decimals): raw price is 3000e6 / 1e18.
+        let raw = 3000e6 / 1e18;
+        let scale = 10f64.powi(18 - 6);
+        let price = to_decimal_price(raw * scale).unwrap();
+        assert_eq!(price, Decimal::from(3000));
+    }
+
+    #[test]
+    fn test_to_decimal_price_rejects_non_finite() {
+        assert!(to_decimal_price(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_execution_price() {
+        let price = execution_price(Decimal::from(2), Decimal::from_str("6000.5").unwrap()).unwrap();
+        assert_eq!(price, Decimal::from_str("3000.25").unwrap());
+        assert!(execution_price(Decimal::ZERO, Decimal::ONE).is_err());
+    }
+
+    #[test]
+    fn test_price_impact_percent() {
+        let impact = price_impact_percent(Decimal::from(3000), Decimal::from(2985), Decimal::ZERO);
+        assert_eq!(impact, Decimal::from_str("0.5").unwrap());
+        // A tiny trade through a 1% pool pays the fee but moves nothing.
+        let one_percent = Decimal::ONE;
+        assert_eq!(price_impact_percent(Decimal::from(3000), Decimal::from(2970), one_percent), Decimal::ZERO);
+        // 2955 against 3000 after 1% (2970) is about 0.5% of impact.
+        let impact = price_impact_percent(Decimal::from(3000), Decimal::from(2955), one_percent);
+        assert_eq!(impact, Decimal::from_str("0.5051").unwrap());
+        assert_eq!(price_impact_percent(Decimal::ZERO, Decimal::ONE, Decimal::ZERO), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_route_fee_percent() {
+        assert_eq!(route_fee_percent(&[3000]), Decimal::from_str("0.3").unwrap());
+        // 1 - 0.9995 * 0.997 = 0.34985%
+        assert_eq!(route_fee_percent(&[500, 3000]), Decimal::from_str("0.34985").unwrap());
+        assert_eq!(route_fee_percent(&[]), Decimal::ZERO);
+    }
+}
@@ -1,4 +1,15 @@
+pub mod accounts;
+pub mod block;
+pub mod chains;
 pub mod contracts;
 pub mod decimals;
+pub mod ens;
+pub mod gas;
+pub mod mid_price;
+pub mod multicall;
+pub mod policy;
 pub mod provider;
+pub mod quoter;
+pub mod routing;
 pub mod token_registry;
+pub mod universal_router;
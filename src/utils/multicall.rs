@@ -0,0 +1,180 @@
+use alloy::contract::Error as ContractError;
+use alloy::eips::BlockId;
+use alloy::network::{Ethereum, TransactionBuilder};
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolCall;
+use alloy::transports::TransportError;
+use anyhow::{Context, Result};
+use futures::future::try_join_all;
+
+use crate::utils::chains::MULTICALL3;
+use crate::utils::contracts::Multicall3;
+
+/// Most calls sent in one `aggregate3`; larger batches are split to stay under RPC gas caps.
+/// Sized for cheap view calls; callers of heavy calls pass a smaller size to [`aggregate_chunks`].
+const MAX_BATCH_SIZE: usize = 100;
+
+/// One read in a batch: ABI-encoded `data` called on `target`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub target: Address,
+    pub data: Bytes,
+}
+
+impl Call {
+    pub fn new(target: Address, call: &impl SolCall) -> Self {
+        Self {
+            target,
+            data: call.abi_encode().into(),
+        }
+    }
+}
+
+/// Run `calls` at `block` through Multicall3 `aggregate3`, allowing each call to fail on its own.
+/// Returns every call's return data in order, or `None` where it reverted. Falls back to one
+/// `eth_call` per call where Multicall3 is not deployed (at `block`).
+pub async fn aggregate(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    calls: &[Call],
+) -> Result<Vec<Option<Bytes>>> {
+    aggregate_chunks(provider, block, calls, MAX_BATCH_SIZE).await
+}
+
+/// [`aggregate`] with at most `batch_size` calls per `aggregate3`, for calls heavy enough that
+/// the default batch could exceed the RPC's `eth_call` gas cap. A batch that still runs out of gas
+/// is sent one call at a time.
+pub async fn aggregate_chunks(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    calls: &[Call],
+    batch_size: usize,
+) -> Result<Vec<Option<Bytes>>> {
+    if calls.len() <= 1 {
+        return call_each(provider, block, calls).await;
+    }
+    let batches = try_join_all(
+        calls
+            .chunks(batch_size.max(1))
+            .map(|batch| aggregate_batch(provider, block, batch)),
+    )
+    .await?;
+    Ok(batches.into_iter().flatten().collect())
+}
+
+/// [`aggregate`] for a batch of one kind of call, decoding each result; calls that revert or
+/// return undecodable data give `None`.
+pub async fn aggregate_calls<C: SolCall>(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    calls: &[(Address, C)],
+) -> Result<Vec<Option<C::Return>>> {
+    let encoded: Vec<Call> = calls.iter().map(|(target, call)| Call::new(*target, call)).collect();
+    let results = aggregate(provider, block, &encoded).await?;
+    Ok(results.iter().map(|output| decode::<C>(output.as_ref())).collect())
+}
+
+/// Decode the return data of a call made through [`aggregate`].
+pub fn decode<C: SolCall>(output: Option<&Bytes>) -> Option<C::Return> {
+    C::abi_decode_returns(output?).ok()
+}
+
+async fn aggregate_batch(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    calls: &[Call],
+) -> Result<Vec<Option<Bytes>>> {
+    let call3s = calls
+        .iter()
+        .map(|call| Multicall3::Call3 {
+            target: call.target,
+            allowFailure: true,
+            callData: call.data.clone(),
+        })
+        .collect();
+
+    match Multicall3::new(MULTICALL3, provider).aggregate3(call3s).block(block).call().await {
+        Ok(results) => {
+            tracing::trace!("Multicall3 batch of {} calls at {}", calls.len(), block);
+            Ok(results
+                .into_iter()
+                .map(|result| result.success.then_some(result.returnData))
+                .collect())
+        }
+        // Calling an address without code succeeds with empty output.
+        Err(ContractError::ZeroData(..)) => {
+            tracing::debug!("Multicall3 is not deployed at {}; sending {} calls one by one", block, calls.len());
+            call_each(provider, block, calls).await
+        }
+        Err(ContractError::TransportError(e)) if is_out_of_gas(&e) => {
+            tracing::debug!("Multicall3 batch of {} calls ran out of gas; sending them one by one: {}", calls.len(), e);
+            call_each(provider, block, calls).await
+        }
+        Err(e) => Err(e).context(format!("Failed to batch {} calls through Multicall3", calls.len())),
+    }
+}
+
+/// Whether an `eth_call` was rejected for running out of gas, or for needing more than the RPC's
+/// gas cap; nodes word this differently, but always mention gas.
+fn is_out_of_gas(error: &TransportError) -> bool {
+    error
+        .as_error_resp()
+        .is_some_and(|resp| resp.message.to_lowercase().contains("gas"))
+}
+
+/// Send each call as its own `eth_call`, concurrently; reverts give `None`.
+async fn call_each(
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    calls: &[Call],
+) -> Result<Vec<Option<Bytes>>> {
+    try_join_all(calls.iter().map(|call| async move {
+        let tx = TransactionRequest::default()
+            .with_to(call.target)
+            .with_input(call.data.clone());
+        match provider.call(tx).block(block).await {
+            Ok(output) => Ok(Some(output)),
+            Err(e) if e.is_error_resp() => Ok(None),
+            Err(e) => Err(e).context(format!("Failed to call {}", call.target)),
+        }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::contracts::IERC20;
+    use alloy::primitives::{U256, address};
+
+    #[test]
+    fn test_call_encodes_selector_and_arguments() {
+        let account = address!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+        let call = Call::new(MULTICALL3, &IERC20::balanceOfCall { account });
+        assert_eq!(call.target, MULTICALL3);
+        assert_eq!(call.data[..4], IERC20::balanceOfCall::SELECTOR);
+        assert_eq!(call.data.len(), 4 + 32);
+    }
+
+    #[test]
+    fn test_out_of_gas_errors() {
+        let error = |message: &str| {
+            let payload = serde_json::json!({ "code": -32000, "message": message });
+            TransportError::ErrorResp(serde_json::from_value(payload).unwrap())
+        };
+        assert!(is_out_of_gas(&error("out of gas")));
+        assert!(is_out_of_gas(&error("gas required exceeds allowance (30000000)")));
+        assert!(!is_out_of_gas(&error("execution reverted")));
+        assert!(!is_out_of_gas(&TransportError::local_usage_str("timeout")));
+    }
+
+    #[test]
+    fn test_decode_failed_and_malformed_results() {
+        let output = Bytes::from(IERC20::balanceOfCall::abi_encode_returns(&U256::from(42)));
+        assert_eq!(decode::<IERC20::balanceOfCall>(Some(&output)), Some(U256::from(42)));
+        assert_eq!(decode::<IERC20::balanceOfCall>(None), None);
+        assert_eq!(decode::<IERC20::balanceOfCall>(Some(&Bytes::new())), None);
+    }
+}
@@ -0,0 +1,721 @@
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, Bytes};
+use alloy::providers::{DynProvider, Provider};
+use alloy::signers::local::PrivateKeySigner;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::utils::accounts::Accounts;
+use crate::utils::chains::{CHAIN_PROFILES, ChainProfile};
+use crate::utils::contracts::IAllowanceTransfer::PermitSingle;
+use crate::utils::provider::signing_provider;
+use crate::utils::token_registry::{ListedToken, listed_metadata, listed_tokens};
+use crate::utils::universal_router::sign_permit;
+
+/// Spending caps of one token, in token units (e.g., '1.5' ETH).
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenLimits {
+    /// Most one transaction may spend.
+    #[serde(default)]
+    pub max_per_transaction: Option<Decimal>,
+    /// Most all transactions of one UTC day may spend together.
+    #[serde(default)]
+    pub max_per_day: Option<Decimal>,
+}
+
+/// Rules every transaction must pass before it is signed, read from a JSON policy file. Tokens are
+/// named by address, by the chain's native symbol, or by a symbol that names exactly one listed
+/// token on each chain. Rules left out do not restrict anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRules {
+    /// Only these tokens may be spent or received.
+    #[serde(default)]
+    pub allowed_tokens: Option<Vec<String>>,
+    /// Spending caps per token.
+    #[serde(default)]
+    pub token_limits: HashMap<String, TokenLimits>,
+    /// Only these contracts may be sent swaps.
+    #[serde(default)]
+    pub allowed_routers: Option<Vec<Address>>,
+    /// Only these addresses may be given allowances or paid; the signer itself always may.
+    #[serde(default)]
+    pub allowed_counterparties: Option<Vec<Address>>,
+    /// Highest slippage tolerance a swap may be sent with, in percent.
+    #[serde(default)]
+    pub max_slippage_percent: Option<Decimal>,
+    /// Highest price impact a swap may be sent with, in percent.
+    #[serde(default)]
+    pub max_price_impact_percent: Option<Decimal>,
+}
+
+/// The policy rule a denied transaction broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    AllowedTokens,
+    MaxPerTransaction,
+    MaxPerDay,
+    AllowedRouters,
+    AllowedCounterparties,
+    MaxSlippagePercent,
+    MaxPriceImpactPercent,
+}
+
+/// Why the policy denied a transaction; returned to MCP clients as structured error data.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PolicyViolation {
+    pub rule: PolicyRule,
+    pub message: String,
+    /// The limit that was exceeded, when the rule has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<String>,
+    /// The value that exceeded it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Denied by spending policy: {}", self.message)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// A token a transaction spends or receives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyToken {
+    /// `None` for the native asset.
+    pub address: Option<Address>,
+    /// Native symbol, or the symbol the token lists give the address, for messages; rules never
+    /// match on it.
+    pub symbol: Option<String>,
+}
+
+impl PolicyToken {
+    /// Look up the listed symbol of `address` on `chain`.
+    pub async fn resolve(chain: &ChainProfile, address: Option<Address>) -> Result<Self> {
+        let symbol = match address {
+            Some(address) => listed_metadata(chain.chain_id, address).await?.map(|token| token.symbol),
+            None => Some(chain.native_symbol.to_string()),
+        };
+        Ok(Self { address, symbol })
+    }
+}
+
+impl fmt::Display for PolicyToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.symbol, self.address) {
+            (Some(symbol), Some(address)) => write!(f, "{} ({})", symbol, address),
+            (Some(symbol), None) => write!(f, "{}", symbol),
+            (None, Some(address)) => write!(f, "{}", address),
+            (None, None) => write!(f, "native asset"),
+        }
+    }
+}
+
+/// What a transaction is about to do, as the policy judges it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spend {
+    pub chain_id: u64,
+    pub token_in: PolicyToken,
+    /// Most of `token_in` the transaction may spend, in token units.
+    pub amount_in: Decimal,
+    pub token_out: PolicyToken,
+    /// Contract the transaction is sent to.
+    pub router: Address,
+    /// Addresses given an allowance or paid, other than the signer.
+    pub counterparties: Vec<Address>,
+    pub slippage_percent: Decimal,
+    pub price_impact_percent: Decimal,
+}
+
+// Spending of one token on one chain, keyed by chain id and token (`None` for native).
+type LedgerKey = (u64, Option<Address>);
+
+/// Amount spent per token on the UTC day it was recorded.
+type Ledger = HashMap<LedgerKey, (NaiveDate, Decimal)>;
+
+/// One token's spending as written to the ledger file.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct LedgerEntry {
+    chain_id: u64,
+    /// `None` for the native asset.
+    token: Option<Address>,
+    day: NaiveDate,
+    #[serde(with = "rust_decimal::serde::str")]
+    spent: Decimal,
+}
+
+/// What each symbol key of the rules names, per chain id: a token address, or `None` for the
+/// native asset. Chains where a symbol names nothing are left out.
+type SymbolKeys = HashMap<String, HashMap<u64, Option<Address>>>;
+
+/// Spending rules together with what was spent under them today.
+#[derive(Debug, Default)]
+pub struct Policy {
+    rules: PolicyRules,
+    symbols: SymbolKeys,
+    ledger: Mutex<Ledger>,
+    /// File the ledger is written to on every change, so the daily caps survive restarts; `None`
+    /// keeps it in memory only.
+    ledger_path: Option<PathBuf>,
+}
+
+impl Policy {
+    /// Load the policy file at `path`, resolving its token symbols against the token lists, and
+    /// the spending ledger kept next to it (`policy.json` keeps its ledger in
+    /// `policy.ledger.json`), which is created on the first spend.
+    pub async fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .context(format!("Failed to read spending policy {}", path.display()))?;
+        let rules = serde_json::from_str(&json)
+            .context(format!("Invalid spending policy {}", path.display()))?;
+        let mut listed = HashMap::new();
+        for chain in CHAIN_PROFILES {
+            listed.insert(chain.chain_id, listed_tokens(chain.chain_id).await?);
+        }
+        let policy = Self::with_listed(rules, &listed)
+            .context(format!("Invalid spending policy {}", path.display()))?;
+
+        let ledger_path = path.with_extension("ledger.json");
+        let ledger = read_ledger(&ledger_path)?;
+        tracing::info!("Spending ledger: {} ({} tokens spent today)", ledger_path.display(), ledger.len());
+        Ok(Self {
+            ledger: Mutex::new(ledger),
+            ledger_path: Some(ledger_path),
+            ..policy
+        })
+    }
+
+    /// Policy enforcing `rules`, with their symbol keys resolved against the tokens `listed` per
+    /// chain id. A symbol must be a chain's native symbol or the symbol of exactly one listed
+    /// token there, so a list labelling another contract the same way cannot pass for it; a
+    /// symbol that is ambiguous on any chain, or names nothing on every chain, is refused.
+    fn with_listed(rules: PolicyRules, listed: &HashMap<u64, Vec<ListedToken>>) -> Result<Self> {
+        let keys = rules.allowed_tokens.iter().flatten().chain(rules.token_limits.keys());
+        let mut symbols = SymbolKeys::new();
+        for key in keys {
+            let key = key.trim();
+            if Address::from_str(key).is_ok() || symbols.contains_key(&key.to_uppercase()) {
+                continue;
+            }
+            let mut chains = HashMap::new();
+            for chain in CHAIN_PROFILES {
+                if chain.native_symbol.eq_ignore_ascii_case(key) {
+                    chains.insert(chain.chain_id, None);
+                    continue;
+                }
+                let tokens = listed.get(&chain.chain_id).map(Vec::as_slice).unwrap_or_default();
+                let matching: Vec<Address> = tokens
+                    .iter()
+                    .filter(|token| token.symbol.eq_ignore_ascii_case(key))
+                    .map(|token| token.address)
+                    .collect();
+                match matching.as_slice() {
+                    [] => {}
+                    [address] => {
+                        chains.insert(chain.chain_id, Some(*address));
+                    }
+                    _ => bail!(
+                        "Token symbol '{}' is ambiguous on {}: candidates are {}; name the token by address instead",
+                        key,
+                        chain.name,
+                        matching.iter().map(Address::to_string).collect::<Vec<_>>().join(", ")
+                    ),
+                }
+            }
+            if chains.is_empty() {
+                bail!("Token symbol '{}' names no listed token on any chain; name the token by address instead", key);
+            }
+            symbols.insert(key.to_uppercase(), chains);
+        }
+        Ok(Self {
+            rules,
+            symbols,
+            ..Self::default()
+        })
+    }
+
+    /// Check `spend` by `sender` against every rule and, if it passes, hand out the signer of the
+    /// configured account at `sender`. This is the only way to a signer, so every signature (swap,
+    /// approval or permit) is authorized here first. The spend counts towards today's spending
+    /// until the signer is dropped unconfirmed.
+    pub fn authorize<'a>(
+        &'a self,
+        accounts: &Accounts,
+        sender: Address,
+        spend: &Spend,
+    ) -> Result<AuthorizedSigner<'a>> {
+        let signer = accounts
+            .signer(sender)
+            .context(format!("No configured account signs for {}", sender))?
+            .clone();
+        let reservation = self.reserve(spend)?;
+        Ok(AuthorizedSigner { signer, reservation })
+    }
+
+    /// Check `spend` against every rule and, if it passes, count its amount towards today's
+    /// spending until the returned reservation is dropped unconfirmed. The ledger file is written
+    /// before this returns, and a spend that cannot be recorded is refused.
+    fn reserve(&self, spend: &Spend) -> Result<SpendReservation<'_>> {
+        self.check_static(spend)?;
+
+        let key = (spend.chain_id, spend.token_in.address);
+        let today = Utc::now().date_naive();
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        let spent = match ledger.get(&key) {
+            Some((day, spent)) if *day == today => *spent,
+            _ => Decimal::ZERO,
+        };
+        for limits in self.token_limits(spend) {
+            if let Some(limit) = limits.max_per_day {
+                let total = spent + spend.amount_in;
+                if total > limit {
+                    return Err(anyhow!(PolicyViolation {
+                        rule: PolicyRule::MaxPerDay,
+                        message: format!(
+                            "spending {} {} would bring today's spending to {}, above the daily cap of {}",
+                            spend.amount_in, spend.token_in, total, limit
+                        ),
+                        limit: Some(limit.to_string()),
+                        actual: Some(total.to_string()),
+                    }));
+                }
+            }
+        }
+        let previous = ledger.insert(key, (today, spent + spend.amount_in));
+        if let Err(e) = self.save(&ledger) {
+            match previous {
+                Some(previous) => ledger.insert(key, previous),
+                None => ledger.remove(&key),
+            };
+            return Err(e.context("Refusing to sign a spend the ledger cannot record"));
+        }
+
+        Ok(SpendReservation {
+            policy: self,
+            key,
+            day: today,
+            amount: spend.amount_in,
+            confirmed: false,
+        })
+    }
+
+    /// Every rule that does not depend on earlier spending.
+    fn check_static(&self, spend: &Spend) -> Result<(), PolicyViolation> {
+        let rules = &self.rules;
+        if let Some(allowed) = &rules.allowed_tokens {
+            for token in [&spend.token_in, &spend.token_out] {
+                if !allowed.iter().any(|key| self.names(key, spend.chain_id, token)) {
+                    return Err(PolicyViolation {
+                        rule: PolicyRule::AllowedTokens,
+                        message: format!("token {} is not in the allowed tokens", token),
+                        limit: None,
+                        actual: Some(token.to_string()),
+                    });
+                }
+            }
+        }
+
+        for limits in self.token_limits(spend) {
+            if let Some(limit) = limits.max_per_transaction
+                && spend.amount_in > limit
+            {
+                return Err(PolicyViolation {
+                    rule: PolicyRule::MaxPerTransaction,
+                    message: format!(
+                        "spending up to {} {} is above the per-transaction cap of {}",
+                        spend.amount_in, spend.token_in, limit
+                    ),
+                    limit: Some(limit.to_string()),
+                    actual: Some(spend.amount_in.to_string()),
+                });
+            }
+        }
+
+        if let Some(allowed) = &rules.allowed_routers
+            && !allowed.contains(&spend.router)
+        {
+            return Err(PolicyViolation {
+                rule: PolicyRule::AllowedRouters,
+                message: format!("router {} is not in the allowed routers", spend.router),
+                limit: None,
+                actual: Some(spend.router.to_string()),
+            });
+        }
+
+        if let Some(allowed) = &rules.allowed_counterparties
+            && let Some(counterparty) = spend.counterparties.iter().find(|c| !allowed.contains(c))
+        {
+            return Err(PolicyViolation {
+                rule: PolicyRule::AllowedCounterparties,
+                message: format!("counterparty {} is not in the allowed counterparties", counterparty),
+                limit: None,
+                actual: Some(counterparty.to_string()),
+            });
+        }
+
+        if let Some(limit) = rules.max_slippage_percent
+            && spend.slippage_percent > limit
+        {
+            return Err(PolicyViolation {
+                rule: PolicyRule::MaxSlippagePercent,
+                message: format!(
+                    "slippage tolerance of {}% is above the maximum of {}%",
+                    spend.slippage_percent, limit
+                ),
+                limit: Some(limit.to_string()),
+                actual: Some(spend.slippage_percent.to_string()),
+            });
+        }
+
+        if let Some(limit) = rules.max_price_impact_percent
+            && spend.price_impact_percent > limit
+        {
+            return Err(PolicyViolation {
+                rule: PolicyRule::MaxPriceImpactPercent,
+                message: format!(
+                    "price impact of {}% is above the maximum of {}%",
+                    spend.price_impact_percent, limit
+                ),
+                limit: Some(limit.to_string()),
+                actual: Some(spend.price_impact_percent.to_string()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the rule key `key` names `token` on `chain_id`: by address, or by a symbol that
+    /// resolved to it when the policy was loaded.
+    fn names(&self, key: &str, chain_id: u64, token: &PolicyToken) -> bool {
+        let key = key.trim();
+        match Address::from_str(key) {
+            Ok(address) => token.address == Some(address),
+            Err(_) => self
+                .symbols
+                .get(&key.to_uppercase())
+                .and_then(|chains| chains.get(&chain_id))
+                .is_some_and(|named| *named == token.address),
+        }
+    }
+
+    /// Caps that apply to `token_in`; a token named twice (e.g., by symbol and address) gets both.
+    fn token_limits<'a>(&'a self, spend: &'a Spend) -> impl Iterator<Item = &'a TokenLimits> {
+        self.rules
+            .token_limits
+            .iter()
+            .filter(|(key, _)| self.names(key, spend.chain_id, &spend.token_in))
+            .map(|(_, limits)| limits)
+    }
+
+    fn release(&self, key: LedgerKey, day: NaiveDate, amount: Decimal) {
+        let mut ledger = self.ledger.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((recorded, spent)) = ledger.get_mut(&key)
+            && *recorded == day
+        {
+            *spent = (*spent - amount).max(Decimal::ZERO);
+            // The file keeps the higher total, which only makes the cap stricter.
+            if let Err(e) = self.save(&ledger) {
+                tracing::warn!("Released spending is still counted in the ledger file: {:#}", e);
+            }
+        }
+    }
+
+    /// Write today's entries of `ledger` to the ledger file, if any, replacing it atomically.
+    fn save(&self, ledger: &Ledger) -> Result<()> {
+        let Some(path) = &self.ledger_path else {
+            return Ok(());
+        };
+        let today = Utc::now().date_naive();
+        let entries: Vec<LedgerEntry> = ledger
+            .iter()
+            .filter(|(_, (day, _))| *day == today)
+            .map(|(&(chain_id, token), &(day, spent))| LedgerEntry {
+                chain_id,
+                token,
+                day,
+                spent,
+            })
+            .collect();
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(&entries)?)
+            .context(format!("Failed to write spending ledger {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .context(format!("Failed to write spending ledger {}", path.display()))
+    }
+}
+
+/// Read the ledger file at `path`; a missing file is an empty ledger.
+fn read_ledger(path: &Path) -> Result<Ledger> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Ledger::new()),
+        Err(e) => return Err(e).context(format!("Failed to read spending ledger {}", path.display())),
+    };
+    let entries: Vec<LedgerEntry> = serde_json::from_str(&json)
+        .context(format!("Invalid spending ledger {}", path.display()))?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| ((entry.chain_id, entry.token), (entry.day, entry.spent)))
+        .collect())
+}
+
+/// The signer of an account, released by [`Policy::authorize`] for one authorized spend, together
+/// with the spending it recorded. Dropping it before [`confirm`](Self::confirm) gives the amount
+/// back, for transactions that were never sent.
+#[must_use = "dropping an authorized signer releases the spending it recorded"]
+#[derive(Debug)]
+pub struct AuthorizedSigner<'a> {
+    signer: PrivateKeySigner,
+    reservation: SpendReservation<'a>,
+}
+
+impl AuthorizedSigner<'_> {
+    /// Sign a Permit2 `permit` as EIP-712 typed data.
+    pub fn sign_permit(&self, chain: &ChainProfile, permit: &PermitSingle) -> Result<Bytes> {
+        sign_permit(&self.signer, chain, permit)
+    }
+
+    /// Provider signing and sending transactions from this account over `provider`'s connection.
+    pub fn wallet(&self, provider: &DynProvider) -> impl Provider<Ethereum> + use<> {
+        signing_provider(provider, self.signer.clone())
+    }
+
+    /// Keep the spending recorded, once the transaction was mined or may still be.
+    pub fn confirm(self) {
+        self.reservation.confirm();
+    }
+}
+
+/// Today's spending recorded for an authorized transaction, released when dropped unconfirmed.
+#[derive(Debug)]
+struct SpendReservation<'a> {
+    policy: &'a Policy,
+    key: LedgerKey,
+    day: NaiveDate,
+    amount: Decimal,
+    confirmed: bool,
+}
+
+impl SpendReservation<'_> {
+    fn confirm(mut self) {
+        self.confirmed = true;
+    }
+}
+
+impl Drop for SpendReservation<'_> {
+    fn drop(&mut self) {
+        if !self.confirmed {
+            self.policy.release(self.key, self.day, self.amount);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::{ARBITRUM, MAINNET, PERMIT2};
+    use alloy::primitives::address;
+    use rust_decimal::dec;
+
+    fn eth() -> PolicyToken {
+        PolicyToken {
+            address: None,
+            symbol: Some("ETH".to_string()),
+        }
+    }
+
+    fn usdc() -> PolicyToken {
+        PolicyToken {
+            address: Some(MAINNET.usd_token),
+            symbol: Some("USDC".to_string()),
+        }
+    }
+
+    fn eth_to_usdc(amount_in: Decimal) -> Spend {
+        Spend {
+            chain_id: MAINNET.chain_id,
+            token_in: eth(),
+            amount_in,
+            token_out: usdc(),
+            router: MAINNET.universal_router,
+            counterparties: vec![PERMIT2],
+            slippage_percent: dec!(0.5),
+            price_impact_percent: dec!(0.1),
+        }
+    }
+
+    fn listed(symbol: &str, address: Address) -> ListedToken {
+        ListedToken {
+            chain_id: MAINNET.chain_id,
+            address,
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            decimals: None,
+            source: "test".to_string(),
+        }
+    }
+
+    /// Mainnet lists USDC and DAI; no other chain lists anything.
+    fn mainnet_listing() -> HashMap<u64, Vec<ListedToken>> {
+        let dai = address!("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+        HashMap::from([(MAINNET.chain_id, vec![listed("USDC", MAINNET.usd_token), listed("DAI", dai)])])
+    }
+
+    fn policy(json: &str) -> Policy {
+        Policy::with_listed(serde_json::from_str(json).unwrap(), &mainnet_listing()).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("eth_mcp_server_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn denied_rule(policy: &Policy, spend: &Spend) -> Option<PolicyRule> {
+        let err = policy.reserve(spend).err()?;
+        err.downcast_ref::<PolicyViolation>().map(|violation| violation.rule)
+    }
+
+    #[test]
+    fn test_parse_policy_file() {
+        let policy: PolicyRules = serde_json::from_str(
+            r#"{
+                "allowed_tokens": ["ETH", "USDC"],
+                "token_limits": { "ETH": { "max_per_transaction": "0.5", "max_per_day": "2" } },
+                "allowed_routers": ["0x66a9893cC07D91D95644AEDD05D03f95e1dBA8Af"],
+                "max_slippage_percent": "1"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(policy.token_limits["ETH"].max_per_day, Some(dec!(2)));
+        assert_eq!(policy.allowed_counterparties, None);
+        assert_eq!(policy.max_slippage_percent, Some(dec!(1)));
+
+        let typo = serde_json::from_str::<PolicyRules>(r#"{ "max_slipage_percent": "1" }"#);
+        assert!(typo.is_err());
+    }
+
+    #[test]
+    fn test_empty_policy_allows_everything() {
+        assert!(Policy::default().reserve(&eth_to_usdc(dec!(1000))).is_ok());
+    }
+
+    #[test]
+    fn test_token_keys_match_symbol_or_address() {
+        let policy = policy(r#"{ "allowed_tokens": ["eth", "usdc"] }"#);
+        assert!(policy.names("usdc", MAINNET.chain_id, &usdc()));
+        assert!(policy.names(&MAINNET.usd_token.to_string().to_lowercase(), MAINNET.chain_id, &usdc()));
+        assert!(!policy.names("USDT", MAINNET.chain_id, &usdc()));
+        assert!(policy.names("ETH", MAINNET.chain_id, &eth()));
+        // Symbols only name what they resolved to on the spend's chain.
+        assert!(!policy.names("USDC", ARBITRUM.chain_id, &usdc()));
+    }
+
+    #[test]
+    fn test_symbol_keys_deny_lookalike_tokens() {
+        let lookalike = PolicyToken {
+            address: Some(Address::repeat_byte(0x42)),
+            symbol: Some("USDC".to_string()),
+        };
+        let spend = Spend {
+            token_out: lookalike,
+            ..eth_to_usdc(dec!(1))
+        };
+        let policy = policy(r#"{ "allowed_tokens": ["ETH", "USDC"] }"#);
+        assert_eq!(denied_rule(&policy, &spend), Some(PolicyRule::AllowedTokens));
+        assert!(policy.reserve(&eth_to_usdc(dec!(1))).is_ok());
+    }
+
+    #[test]
+    fn test_ambiguous_or_unlisted_symbols_are_refused() {
+        let rules = |json| serde_json::from_str::<PolicyRules>(json).unwrap();
+        let mut listing = mainnet_listing();
+        listing
+            .get_mut(&MAINNET.chain_id)
+            .unwrap()
+            .push(listed("USDC", Address::repeat_byte(0x42)));
+        let err = Policy::with_listed(rules(r#"{ "allowed_tokens": ["USDC"] }"#), &listing).unwrap_err();
+        assert!(err.to_string().contains("ambiguous"), "{}", err);
+        // The ambiguous symbol can still be named by address.
+        let by_address = format!(r#"{{ "allowed_tokens": ["{}"] }}"#, MAINNET.usd_token);
+        assert!(Policy::with_listed(rules(&by_address), &listing).is_ok());
+
+        let err = Policy::with_listed(rules(r#"{ "token_limits": { "USDT": {} } }"#), &mainnet_listing());
+        assert!(err.unwrap_err().to_string().contains("names no listed token"));
+    }
+
+    #[test]
+    fn test_each_rule_denies() {
+        let spend = eth_to_usdc(dec!(1));
+        let cases = [
+            (r#"{ "allowed_tokens": ["ETH", "DAI"] }"#, PolicyRule::AllowedTokens),
+            (r#"{ "token_limits": { "ETH": { "max_per_transaction": "0.5" } } }"#, PolicyRule::MaxPerTransaction),
+            (r#"{ "token_limits": { "ETH": { "max_per_day": "0.5" } } }"#, PolicyRule::MaxPerDay),
+            (r#"{ "allowed_routers": ["0x0000000000000000000000000000000000000001"] }"#, PolicyRule::AllowedRouters),
+            (r#"{ "allowed_counterparties": [] }"#, PolicyRule::AllowedCounterparties),
+            (r#"{ "max_slippage_percent": "0.3" }"#, PolicyRule::MaxSlippagePercent),
+            (r#"{ "max_price_impact_percent": "0.05" }"#, PolicyRule::MaxPriceImpactPercent),
+        ];
+        for (json, rule) in cases {
+            assert_eq!(denied_rule(&policy(json), &spend), Some(rule), "{}", json);
+        }
+
+        let err = policy(r#"{ "max_slippage_percent": "0.3" }"#).reserve(&spend).unwrap_err();
+        let data = serde_json::to_value(err.downcast_ref::<PolicyViolation>().unwrap()).unwrap();
+        assert_eq!(data["rule"], "max_slippage_percent");
+        assert_eq!(data["limit"], "0.3");
+        assert_eq!(data["actual"], "0.5");
+    }
+
+    #[tokio::test]
+    async fn test_ledger_survives_a_restart() {
+        let dir = temp_dir("policy_ledger");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+        std::fs::write(&path, r#"{ "token_limits": { "ETH": { "max_per_day": "1" } } }"#).unwrap();
+
+        let policy = Policy::load(&path).await.unwrap();
+        policy.reserve(&eth_to_usdc(dec!(0.6))).unwrap().confirm();
+        // Written before the reservation is confirmed, and rewritten when one is released.
+        drop(policy.reserve(&eth_to_usdc(dec!(0.3))).unwrap());
+
+        let restarted = Policy::load(&path).await.unwrap();
+        assert_eq!(denied_rule(&restarted, &eth_to_usdc(dec!(0.6))), Some(PolicyRule::MaxPerDay));
+        assert!(restarted.reserve(&eth_to_usdc(dec!(0.4))).is_ok());
+
+        std::fs::write(dir.join("policy.ledger.json"), "not json").unwrap();
+        assert!(Policy::load(&path).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_authorize_needs_a_configured_sender() {
+        let sender = Address::repeat_byte(0x42);
+        let err = Policy::default()
+            .authorize(&Accounts::default(), sender, &eth_to_usdc(dec!(1)))
+            .unwrap_err();
+        assert!(err.to_string().contains(&sender.to_string()));
+    }
+
+    #[test]
+    fn test_daily_cap_counts_confirmed_spending() {
+        let policy = policy(r#"{ "token_limits": { "ETH": { "max_per_day": "1" } } }"#);
+        policy.reserve(&eth_to_usdc(dec!(0.6))).unwrap().confirm();
+        assert_eq!(denied_rule(&policy, &eth_to_usdc(dec!(0.6))), Some(PolicyRule::MaxPerDay));
+
+        // Unconfirmed reservations are released when dropped.
+        drop(policy.reserve(&eth_to_usdc(dec!(0.4))).unwrap());
+        policy.reserve(&eth_to_usdc(dec!(0.4))).unwrap().confirm();
+        assert_eq!(denied_rule(&policy, &eth_to_usdc(dec!(0.1))), Some(PolicyRule::MaxPerDay));
+    }
+}
@@ -1,89 +1,166 @@
 use alloy::network::Ethereum;
-use alloy::primitives::Address;
-use alloy::providers::{Provider, ProviderBuilder};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
 use alloy::signers::local::PrivateKeySigner;
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-const ETH_PRIVATE_KEY: &str = "ETH_PRIVATE_KEY";
-const ETH_RPC_URL: &str = "ETH_RPC_URL";
-pub const CHAIN_ID: u64 = 1;
+use crate::utils::chains::{CHAIN_PROFILES, ChainProfile};
 
-fn make_wallet() -> Result<PrivateKeySigner> {
-    let private_key_string = std::env::var(ETH_PRIVATE_KEY)?;
-    let signer: PrivateKeySigner = private_key_string.parse()?;
-    Ok(signer)
+fn rpc_url(chain: &ChainProfile) -> Result<String> {
+    match std::env::var(chain.rpc_url_env) {
+        Ok(url) => Ok(url),
+        Err(_) => chain.default_rpc_url.map(str::to_string).context(format!(
+            "{} is not set; an RPC URL is required for chain '{}'",
+            chain.rpc_url_env, chain.name
+        )),
+    }
 }
 
-pub fn make_provider() -> Result<impl Provider<Ethereum>> {
-    tracing::trace!("Creating provider with RPC_URL from environment");
-    let rpc_url = std::env::var(ETH_RPC_URL)?;
+// Read-only provider: tools only read state or simulate via `eth_call`, so no signer is attached.
+pub fn make_provider(chain: &ChainProfile) -> Result<impl Provider<Ethereum>> {
+    tracing::trace!("Creating provider with {} from environment", chain.rpc_url_env);
+    let rpc_url = rpc_url(chain)?;
     tracing::trace!("RPC URL: {}", rpc_url);
-    let wallet = make_wallet()?;
-    tracing::trace!("Wallet address: {}", wallet.address());
     let provider = ProviderBuilder::new()
-        .wallet(wallet)
-        .with_chain_id(CHAIN_ID)
+        .with_chain_id(chain.chain_id)
         .connect_http(Url::parse(&rpc_url)?);
-    tracing::trace!("Provider created successfully with chain_id: {}", CHAIN_ID);
+    tracing::trace!("Provider created successfully with chain_id: {}", chain.chain_id);
     Ok(provider)
 }
 
-pub fn get_wallet_address() -> Result<Address> {
-    let wallet = make_wallet()?;
-    Ok(wallet.address())
+/// Provider that signs and sends transactions from `signer` over `provider`'s connection, filling
+/// in the nonce, gas limit and EIP-1559 fees. Only reachable through the spending policy's
+/// authorized signer.
+pub(super) fn signing_provider(
+    provider: &DynProvider,
+    signer: PrivateKeySigner,
+) -> impl Provider<Ethereum> + use<> {
+    ProviderBuilder::new().wallet(signer).connect_provider(provider.clone())
+}
+
+/// Long-lived providers for every configured chain, built once at startup and shared by all tool calls.
+/// Each provider keeps its own HTTP client, so connections to the RPC endpoint are reused.
+#[derive(Debug, Clone, Default)]
+pub struct Providers {
+    providers: Arc<HashMap<u64, DynProvider>>,
+}
+
+impl Providers {
+    /// Connect to every chain with an RPC URL and verify that the endpoint serves the expected chain id.
+    /// Explicitly configured endpoints must be valid; chains only reachable via their default URL
+    /// (e.g. a local devnet) are skipped if they are down.
+    pub async fn connect() -> Result<Self> {
+        let mut providers = HashMap::new();
+
+        for chain in CHAIN_PROFILES {
+            let configured = std::env::var(chain.rpc_url_env).is_ok();
+            if !configured && chain.default_rpc_url.is_none() {
+                tracing::debug!("Skipping chain {}: {} is not set", chain.name, chain.rpc_url_env);
+                continue;
+            }
+
+            match connect_chain(chain).await {
+                Ok(provider) => {
+                    tracing::info!("Connected to chain {} (chain id {})", chain.name, chain.chain_id);
+                    providers.insert(chain.chain_id, provider);
+                }
+                Err(e) if configured => return Err(e),
+                Err(e) => tracing::warn!("Skipping chain {}: {:#}", chain.name, e),
+            }
+        }
+
+        if providers.is_empty() {
+            bail!(
+                "No chain is available; set an RPC URL such as {}",
+                CHAIN_PROFILES.map(|c| c.rpc_url_env).join(", ")
+            );
+        }
+
+        Ok(Self {
+            providers: Arc::new(providers),
+        })
+    }
+
+    pub fn get(&self, chain: &ChainProfile) -> Result<&DynProvider> {
+        self.providers.get(&chain.chain_id).context(format!(
+            "Chain '{}' is not configured; set {} to enable it",
+            chain.name, chain.rpc_url_env
+        ))
+    }
+}
+
+async fn connect_chain(chain: &'static ChainProfile) -> Result<DynProvider> {
+    let provider = make_provider(chain)?.erased();
+    let remote_chain_id = provider
+        .get_chain_id()
+        .await
+        .context(format!("Failed to reach RPC endpoint for chain '{}'", chain.name))?;
+    if remote_chain_id != chain.chain_id {
+        bail!(
+            "{} points to chain id {}, expected {} ({})",
+            chain.rpc_url_env,
+            remote_chain_id,
+            chain.chain_id,
+            chain.name
+        );
+    }
+    Ok(provider)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::chains::{LOCAL, MAINNET};
 
     #[test]
     fn test_make_provider_missing_env() {
         // Clean up environment variables
         unsafe {
-            std::env::remove_var(ETH_RPC_URL);
-            std::env::remove_var(ETH_PRIVATE_KEY);
+            std::env::remove_var(MAINNET.rpc_url_env);
         }
 
-        let result = make_provider();
+        let result = make_provider(&MAINNET);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_make_provider_invalid_url() {
         unsafe {
-            std::env::set_var(ETH_RPC_URL, "not-a-valid-url");
-            std::env::set_var(
-                ETH_PRIVATE_KEY,
-                "0x0000000000000000000000000000000000000000000000000000000000000001",
-            );
+            std::env::set_var(MAINNET.rpc_url_env, "not-a-valid-url");
         }
 
-        let result = make_provider();
+        let result = make_provider(&MAINNET);
         assert!(result.is_err());
 
         // Cleanup
         unsafe {
-            std::env::remove_var(ETH_RPC_URL);
-            std::env::remove_var(ETH_PRIVATE_KEY);
+            std::env::remove_var(MAINNET.rpc_url_env);
         }
     }
 
     #[test]
-    fn test_make_provider_invalid_private_key() {
-        unsafe {
-            std::env::set_var(ETH_RPC_URL, "https://eth.llamarpc.com");
-            std::env::set_var(ETH_PRIVATE_KEY, "invalid-key");
-        }
+    fn test_make_provider_without_private_key() {
+        // Read-only mode: only an RPC URL is needed (LOCAL falls back to its default URL).
+        let result = make_provider(&LOCAL);
+        assert!(result.is_ok());
+    }
 
-        let result = make_provider();
-        assert!(result.is_err());
+    #[test]
+    fn test_providers_get_unconfigured_chain() {
+        let providers = Providers::default();
+        let err = providers.get(&MAINNET).unwrap_err().to_string();
+        assert!(err.contains("not configured"));
+        assert!(err.contains(MAINNET.rpc_url_env));
+    }
 
-        // Cleanup
+    #[test]
+    fn test_rpc_url_falls_back_to_chain_default() {
         unsafe {
-            std::env::remove_var(ETH_RPC_URL);
-            std::env::remove_var(ETH_PRIVATE_KEY);
+            std::env::remove_var(LOCAL.rpc_url_env);
         }
+
+        assert_eq!(rpc_url(&LOCAL).unwrap(), "http://127.0.0.1:8545");
     }
 }
@@ -0,0 +1,179 @@
+use alloy::primitives::aliases::U24;
+use alloy::primitives::{Address, Bytes, U256, Uint};
+use alloy::sol_types::SolCall;
+use anyhow::Result;
+
+use crate::utils::chains::{ChainProfile, QuoterVersion};
+use crate::utils::contracts::IQuoterV2::{QuoteExactInputSingleParams, QuoteExactOutputSingleParams};
+use crate::utils::contracts::{UniswapV3Quoter, UniswapV3QuoterV2};
+use crate::utils::multicall::Call;
+
+/// A Uniswap V3 quote, encoded for whichever quoter contract the chain has deployed.
+// Variants are named after the quoter functions they call.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum V3Quote {
+    /// Output of an exact-input single-pool swap.
+    ExactInputSingle {
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+        amount_in: U256,
+    },
+    /// Input required for an exact-output single-pool swap.
+    ExactOutputSingle {
+        token_in: Address,
+        token_out: Address,
+        fee: U24,
+        amount_out: U256,
+    },
+    /// Output of an exact-input multi-hop swap along an encoded V3 path.
+    ExactInput { path: Bytes, amount_in: U256 },
+    /// Input required for an exact-output multi-hop swap.
+    /// The path must be encoded in reverse order (output token first).
+    ExactOutput { path: Bytes, amount_out: U256 },
+}
+
+impl V3Quote {
+    /// The quoter call for this quote on `chain`, for batching with other reads.
+    pub fn call(&self, chain: &ChainProfile) -> Call {
+        let data = match (chain.quoter_version, self.clone()) {
+            (QuoterVersion::V1, Self::ExactInputSingle { token_in, token_out, fee, amount_in }) => {
+                UniswapV3Quoter::quoteExactInputSingleCall {
+                    tokenIn: token_in,
+                    tokenOut: token_out,
+                    fee,
+                    amountIn: amount_in,
+                    sqrtPriceLimitX96: Uint::ZERO,
+                }
+                .abi_encode()
+            }
+            (QuoterVersion::V1, Self::ExactOutputSingle { token_in, token_out, fee, amount_out }) => {
+                UniswapV3Quoter::quoteExactOutputSingleCall {
+                    tokenIn: token_in,
+                    tokenOut: token_out,
+                    fee,
+                    amountOut: amount_out,
+                    sqrtPriceLimitX96: Uint::ZERO,
+                }
+                .abi_encode()
+            }
+            (QuoterVersion::V1, Self::ExactInput { path, amount_in }) => {
+                UniswapV3Quoter::quoteExactInputCall { path, amountIn: amount_in }.abi_encode()
+            }
+            (QuoterVersion::V1, Self::ExactOutput { path, amount_out }) => {
+                UniswapV3Quoter::quoteExactOutputCall { path, amountOut: amount_out }.abi_encode()
+            }
+            (QuoterVersion::V2, Self::ExactInputSingle { token_in, token_out, fee, amount_in }) => {
+                UniswapV3QuoterV2::quoteExactInputSingleCall {
+                    params: QuoteExactInputSingleParams {
+                        tokenIn: token_in,
+                        tokenOut: token_out,
+                        amountIn: amount_in,
+                        fee,
+                        sqrtPriceLimitX96: Uint::ZERO,
+                    },
+                }
+                .abi_encode()
+            }
+            (QuoterVersion::V2, Self::ExactOutputSingle { token_in, token_out, fee, amount_out }) => {
+                UniswapV3QuoterV2::quoteExactOutputSingleCall {
+                    params: QuoteExactOutputSingleParams {
+                        tokenIn: token_in,
+                        tokenOut: token_out,
+                        amount: amount_out,
+                        fee,
+                        sqrtPriceLimitX96: Uint::ZERO,
+                    },
+                }
+                .abi_encode()
+            }
+            (QuoterVersion::V2, Self::ExactInput { path, amount_in }) => {
+                UniswapV3QuoterV2::quoteExactInputCall { path, amountIn: amount_in }.abi_encode()
+            }
+            (QuoterVersion::V2, Self::ExactOutput { path, amount_out }) => {
+                UniswapV3QuoterV2::quoteExactOutputCall { path, amountOut: amount_out }.abi_encode()
+            }
+        };
+        Call {
+            target: chain.v3_quoter,
+            data: data.into(),
+        }
+    }
+
+    /// The quoted amount (output for exact input, input for exact output) from the quoter's
+    /// return data.
+    pub fn decode(&self, chain: &ChainProfile, output: &[u8]) -> Result<U256> {
+        let amount = match (chain.quoter_version, self) {
+            (QuoterVersion::V1, Self::ExactInputSingle { .. }) => {
+                UniswapV3Quoter::quoteExactInputSingleCall::abi_decode_returns(output)?
+            }
+            (QuoterVersion::V1, Self::ExactOutputSingle { .. }) => {
+                UniswapV3Quoter::quoteExactOutputSingleCall::abi_decode_returns(output)?
+            }
+            (QuoterVersion::V1, Self::ExactInput { .. }) => {
+                UniswapV3Quoter::quoteExactInputCall::abi_decode_returns(output)?
+            }
+            (QuoterVersion::V1, Self::ExactOutput { .. }) => {
+                UniswapV3Quoter::quoteExactOutputCall::abi_decode_returns(output)?
+            }
+            (QuoterVersion::V2, Self::ExactInputSingle { .. }) => {
+                UniswapV3QuoterV2::quoteExactInputSingleCall::abi_decode_returns(output)?.amountOut
+            }
+            (QuoterVersion::V2, Self::ExactOutputSingle { .. }) => {
+                UniswapV3QuoterV2::quoteExactOutputSingleCall::abi_decode_returns(output)?.amountIn
+            }
+            (QuoterVersion::V2, Self::ExactInput { .. }) => {
+                UniswapV3QuoterV2::quoteExactInputCall::abi_decode_returns(output)?.amountOut
+            }
+            (QuoterVersion::V2, Self::ExactOutput { .. }) => {
+                UniswapV3QuoterV2::quoteExactOutputCall::abi_decode_returns(output)?.amountIn
+            }
+        };
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::{BASE, MAINNET};
+
+    fn single_quote() -> V3Quote {
+        V3Quote::ExactInputSingle {
+            token_in: MAINNET.weth,
+            token_out: MAINNET.usd_token,
+            fee: U24::from(500),
+            amount_in: U256::from(10).pow(U256::from(18)),
+        }
+    }
+
+    #[test]
+    fn test_quote_call_matches_quoter_version() {
+        let v1 = single_quote().call(&MAINNET);
+        assert_eq!(v1.target, MAINNET.v3_quoter);
+        assert_eq!(v1.data[..4], UniswapV3Quoter::quoteExactInputSingleCall::SELECTOR);
+
+        let v2 = single_quote().call(&BASE);
+        assert_eq!(v2.target, BASE.v3_quoter);
+        assert_eq!(v2.data[..4], UniswapV3QuoterV2::quoteExactInputSingleCall::SELECTOR);
+    }
+
+    #[test]
+    fn test_decode_quote_for_each_quoter_version() {
+        let amount = U256::from(3_000_000_000u64);
+        let v1 = UniswapV3Quoter::quoteExactInputSingleCall::abi_encode_returns(&amount);
+        assert_eq!(single_quote().decode(&MAINNET, &v1).unwrap(), amount);
+
+        let v2 = UniswapV3QuoterV2::quoteExactInputSingleCall::abi_encode_returns(
+            &UniswapV3QuoterV2::quoteExactInputSingleReturn {
+                amountOut: amount,
+                sqrtPriceX96After: Uint::ZERO,
+                initializedTicksCrossed: 1,
+                gasEstimate: U256::from(80_000),
+            },
+        );
+        assert_eq!(single_quote().decode(&BASE, &v2).unwrap(), amount);
+        assert!(single_quote().decode(&BASE, &[]).is_err());
+    }
+}
@@ -0,0 +1,596 @@
+use alloy::eips::BlockId;
+use alloy::network::Ethereum;
+use alloy::primitives::aliases::U24;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::sol_types::SolCall;
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::utils::chains::{ChainProfile, V2Venue};
+use crate::utils::contracts::{UniswapV2Router, UniswapV3Factory};
+use crate::utils::multicall::{Call, aggregate_calls, aggregate_chunks};
+use crate::utils::quoter::V3Quote;
+
+pub const FEE_TIERS: [u32; 4] = [100, 500, 3000, 10000];
+/// Every V2 style pair charges a flat 0.3%, reported in the same unit as V3 fee tiers.
+pub const V2_FEE: u32 = 3000;
+/// Most quoter calls sent in one `aggregate3`. A quote simulates the whole swap, and a multi-hop
+/// V3 quote crossing many ticks can take a million gas or more, so batches stay far below the
+/// `eth_call` gas caps of public RPCs (often 10M-50M).
+const QUOTE_BATCH_SIZE: usize = 8;
+
+/// Where a route's liquidity lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Venue {
+    UniswapV3,
+    V2(&'static V2Venue),
+}
+
+impl Venue {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Venue::UniswapV3 => "uniswap_v3",
+            Venue::V2(venue) => venue.name,
+        }
+    }
+}
+
+/// A route: `tokens[i] -> tokens[i + 1]` through the pool with fee `fees[i]` on `venue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub venue: Venue,
+    pub tokens: Vec<Address>,
+    pub fees: Vec<U24>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RouteHop {
+    pub token_in: Address,
+    pub token_out: Address,
+    /// Pool fee in hundredths of a bip (e.g., 3000 = 0.3%).
+    pub fee: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteQuote {
+    pub route: Route,
+    /// Output amount for exact-input quotes, required input for exact-output quotes.
+    pub amount: U256,
+}
+
+/// Result of a route search: the overall winner plus the best quote found on each venue.
+#[derive(Debug, Clone)]
+pub struct RouteSearch {
+    pub best: RouteQuote,
+    pub venues: Vec<RouteQuote>,
+}
+
+impl Route {
+    fn v2(venue: &'static V2Venue, tokens: Vec<Address>) -> Self {
+        let fees = vec![fee_tier(V2_FEE); tokens.len() - 1];
+        Route {
+            venue: Venue::V2(venue),
+            tokens,
+            fees,
+        }
+    }
+
+    pub fn is_single_hop(&self) -> bool {
+        self.fees.len() == 1
+    }
+
+    pub fn token_in(&self) -> Address {
+        self.tokens[0]
+    }
+
+    pub fn token_out(&self) -> Address {
+        self.tokens[self.tokens.len() - 1]
+    }
+
+    /// Encode as a V3 path: `token | fee | token | fee | ... | token`.
+    pub fn encode_path(&self) -> Bytes {
+        encode_path(self.tokens.iter(), self.fees.iter())
+    }
+
+    /// Encode the path output token first, as `exactOutput` and `quoteExactOutput` expect.
+    pub fn encode_reversed_path(&self) -> Bytes {
+        encode_path(self.tokens.iter().rev(), self.fees.iter().rev())
+    }
+
+    pub fn hops(&self) -> Vec<RouteHop> {
+        self.tokens
+            .windows(2)
+            .zip(&self.fees)
+            .map(|(pair, fee)| RouteHop {
+                token_in: pair[0],
+                token_out: pair[1],
+                fee: fee.to::<u32>(),
+            })
+            .collect()
+    }
+}
+
+fn encode_path<'a>(
+    tokens: impl Iterator<Item = &'a Address>,
+    fees: impl Iterator<Item = &'a U24>,
+) -> Bytes {
+    let mut path = Vec::new();
+    let mut fees = fees;
+    for token in tokens {
+        path.extend_from_slice(token.as_slice());
+        if let Some(fee) = fees.next() {
+            path.extend_from_slice(&fee.to_be_bytes::<3>());
+        }
+    }
+    Bytes::from(path)
+}
+
+fn fee_tier(fee: u32) -> U24 {
+    U24::from(fee)
+}
+
+fn intermediates(chain: &ChainProfile, token_in: Address, token_out: Address) -> Vec<Address> {
+    chain
+        .route_tokens
+        .iter()
+        .copied()
+        .filter(|&t| t != token_in && t != token_out)
+        .collect()
+}
+
+/// Fee tiers of the V3 pools that exist between each pair of tokens, looked up in one batch.
+async fn pool_fees(
+    chain: &ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    pairs: &[(Address, Address)],
+) -> Vec<Vec<U24>> {
+    let calls: Vec<_> = pairs
+        .iter()
+        .flat_map(|&(token_a, token_b)| {
+            FEE_TIERS.iter().map(move |&fee| {
+                let get_pool = UniswapV3Factory::getPoolCall {
+                    tokenA: token_a,
+                    tokenB: token_b,
+                    fee: fee_tier(fee),
+                };
+                (chain.v3_factory, get_pool)
+            })
+        })
+        .collect();
+    let pools = match aggregate_calls(provider, block, &calls).await {
+        Ok(pools) => pools,
+        Err(e) => {
+            tracing::warn!("Failed to look up V3 pools: {}", e);
+            return vec![Vec::new(); pairs.len()];
+        }
+    };
+
+    pools
+        .chunks(FEE_TIERS.len())
+        .map(|pools| {
+            FEE_TIERS
+                .iter()
+                .zip(pools)
+                .filter(|(_, pool)| pool.is_some_and(|pool| pool != Address::ZERO))
+                .map(|(&fee, _)| fee_tier(fee))
+                .collect()
+        })
+        .collect()
+}
+
+/// Build every direct route and every route through a single intermediate token.
+fn combine_routes(
+    token_in: Address,
+    token_out: Address,
+    direct_fees: &[U24],
+    via: &[(Address, Vec<U24>, Vec<U24>)],
+) -> Vec<Route> {
+    let mut routes: Vec<Route> = direct_fees
+        .iter()
+        .map(|&fee| Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![token_in, token_out],
+            fees: vec![fee],
+        })
+        .collect();
+
+    for (intermediate, first_fees, second_fees) in via {
+        for &first in first_fees {
+            for &second in second_fees {
+                routes.push(Route {
+                    venue: Venue::UniswapV3,
+                    tokens: vec![token_in, *intermediate, token_out],
+                    fees: vec![first, second],
+                });
+            }
+        }
+    }
+
+    routes
+}
+
+/// Discover candidate V3 routes from the pools that actually exist on the chain.
+async fn candidate_v3_routes(
+    chain: &ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    token_in: Address,
+    token_out: Address,
+) -> Vec<Route> {
+    let intermediates = intermediates(chain, token_in, token_out);
+    let mut pairs = vec![(token_in, token_out)];
+    for &intermediate in &intermediates {
+        pairs.push((token_in, intermediate));
+        pairs.push((intermediate, token_out));
+    }
+
+    let mut fees = pool_fees(chain, provider, block, &pairs).await.into_iter();
+    let direct_fees = fees.next().unwrap_or_default();
+    let via: Vec<_> = intermediates
+        .into_iter()
+        .map(|intermediate| {
+            let first = fees.next().unwrap_or_default();
+            let second = fees.next().unwrap_or_default();
+            (intermediate, first, second)
+        })
+        .collect();
+
+    combine_routes(token_in, token_out, &direct_fees, &via)
+}
+
+/// Candidate V2 routes on every venue. Missing pairs simply make the router quote revert.
+fn candidate_v2_routes(chain: &'static ChainProfile, token_in: Address, token_out: Address) -> Vec<Route> {
+    let intermediates = intermediates(chain, token_in, token_out);
+    chain
+        .v2_venues
+        .iter()
+        .flat_map(|venue| {
+            std::iter::once(Route::v2(venue, vec![token_in, token_out])).chain(
+                intermediates
+                    .iter()
+                    .map(move |&intermediate| Route::v2(venue, vec![token_in, intermediate, token_out])),
+            )
+        })
+        .collect()
+}
+
+async fn candidate_routes(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    token_in: Address,
+    token_out: Address,
+) -> Vec<Route> {
+    let mut routes = candidate_v3_routes(chain, provider, block, token_in, token_out).await;
+    routes.extend(candidate_v2_routes(chain, token_in, token_out));
+    tracing::trace!("Found {} candidate routes for {} -> {}", routes.len(), token_in, token_out);
+    routes
+}
+
+/// Which amount of a swap a quote holds fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuoteSide {
+    /// Quote the output for an exact input amount.
+    ExactInput,
+    /// Quote the input required for an exact output amount.
+    ExactOutput,
+}
+
+fn v3_quote(route: &Route, side: QuoteSide, amount: U256) -> V3Quote {
+    match side {
+        QuoteSide::ExactInput if route.is_single_hop() => V3Quote::ExactInputSingle {
+            token_in: route.token_in(),
+            token_out: route.token_out(),
+            fee: route.fees[0],
+            amount_in: amount,
+        },
+        QuoteSide::ExactOutput if route.is_single_hop() => V3Quote::ExactOutputSingle {
+            token_in: route.token_in(),
+            token_out: route.token_out(),
+            fee: route.fees[0],
+            amount_out: amount,
+        },
+        QuoteSide::ExactInput => V3Quote::ExactInput {
+            path: route.encode_path(),
+            amount_in: amount,
+        },
+        QuoteSide::ExactOutput => V3Quote::ExactOutput {
+            path: route.encode_reversed_path(),
+            amount_out: amount,
+        },
+    }
+}
+
+/// The quoter (V3) or router (V2) call quoting `route` for `amount`.
+fn quote_call(chain: &ChainProfile, route: &Route, side: QuoteSide, amount: U256) -> Call {
+    match (route.venue, side) {
+        (Venue::V2(venue), QuoteSide::ExactInput) => Call::new(
+            venue.router,
+            &UniswapV2Router::getAmountsOutCall {
+                amountIn: amount,
+                path: route.tokens.clone(),
+            },
+        ),
+        (Venue::V2(venue), QuoteSide::ExactOutput) => Call::new(
+            venue.router,
+            &UniswapV2Router::getAmountsInCall {
+                amountOut: amount,
+                path: route.tokens.clone(),
+            },
+        ),
+        (Venue::UniswapV3, _) => v3_quote(route, side, amount).call(chain),
+    }
+}
+
+/// The quoted amount from the return data of [`quote_call`].
+fn decode_quote(
+    chain: &ChainProfile,
+    route: &Route,
+    side: QuoteSide,
+    amount: U256,
+    output: &[u8],
+) -> Result<U256> {
+    match (route.venue, side) {
+        (Venue::V2(_), QuoteSide::ExactInput) => {
+            let amounts = UniswapV2Router::getAmountsOutCall::abi_decode_returns(output)?;
+            amounts.last().copied().context("Empty getAmountsOut result")
+        }
+        (Venue::V2(_), QuoteSide::ExactOutput) => {
+            let amounts = UniswapV2Router::getAmountsInCall::abi_decode_returns(output)?;
+            amounts.first().copied().context("Empty getAmountsIn result")
+        }
+        (Venue::UniswapV3, _) => v3_quote(route, side, amount).decode(chain, output),
+    }
+}
+
+/// Quote every route for `amount` in one batch of calls; a route whose quote reverts gets an error.
+async fn quote_routes(
+    chain: &ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    routes: &[Route],
+    side: QuoteSide,
+    amount: U256,
+) -> Result<Vec<Result<U256>>> {
+    let calls: Vec<Call> = routes
+        .iter()
+        .map(|route| quote_call(chain, route, side, amount))
+        .collect();
+    let outputs = aggregate_chunks(provider, block, &calls, QUOTE_BATCH_SIZE)
+        .await
+        .context(format!("Failed to quote {} routes", routes.len()))?;
+
+    Ok(routes
+        .iter()
+        .zip(outputs)
+        .map(|(route, output)| match output {
+            Some(output) => decode_quote(chain, route, side, amount, &output),
+            None => Err(anyhow!("Quote reverted")),
+        })
+        .collect())
+}
+
+/// Pick the overall best quote and the best quote per venue; `better(a, b)` is true if `a` beats `b`.
+fn select_best(
+    routes: Vec<Route>,
+    quotes: Vec<Result<U256>>,
+    better: fn(U256, U256) -> bool,
+) -> Option<RouteSearch> {
+    let mut venues: Vec<RouteQuote> = Vec::new();
+    for (route, quote) in routes.into_iter().zip(quotes) {
+        let amount = match quote {
+            Ok(amount) if amount > U256::ZERO => amount,
+            _ => {
+                tracing::trace!("Route {} {:?}: no liquidity or error", route.venue.name(), route.hops());
+                continue;
+            }
+        };
+        tracing::trace!("Route {} {:?}: quote = {}", route.venue.name(), route.hops(), amount);
+
+        match venues.iter_mut().find(|q| q.route.venue == route.venue) {
+            Some(current) if better(amount, current.amount) => *current = RouteQuote { route, amount },
+            Some(_) => {}
+            None => venues.push(RouteQuote { route, amount }),
+        }
+    }
+
+    let best = venues
+        .iter()
+        .cloned()
+        .reduce(|best, q| if better(q.amount, best.amount) { q } else { best })?;
+    Some(RouteSearch { best, venues })
+}
+
+/// Find the route (V3 or V2, direct or via one intermediate token) yielding the most output for `amount_in`.
+pub async fn find_best_route_exact_input(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+) -> Result<RouteSearch> {
+    let routes = candidate_routes(chain, provider, block, token_in, token_out).await;
+    let quotes = quote_routes(chain, provider, block, &routes, QuoteSide::ExactInput, amount_in).await?;
+
+    match select_best(routes, quotes, |a, b| a > b) {
+        Some(search) => Ok(search),
+        None => {
+            tracing::warn!("No liquidity found for pair {}/{} on any venue", token_in, token_out);
+            bail!("No liquidity found for pair {}/{} in V3 or V2 pools", token_in, token_out)
+        }
+    }
+}
+
+/// Find the route (V3 or V2, direct or via one intermediate token) requiring the least input for `amount_out`.
+pub async fn find_best_route_exact_output(
+    chain: &'static ChainProfile,
+    provider: &impl Provider<Ethereum>,
+    block: BlockId,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+) -> Result<RouteSearch> {
+    let routes = candidate_routes(chain, provider, block, token_in, token_out).await;
+    let quotes = quote_routes(chain, provider, block, &routes, QuoteSide::ExactOutput, amount_out).await?;
+
+    match select_best(routes, quotes, |a, b| a < b) {
+        Some(search) => Ok(search),
+        None => {
+            tracing::warn!("No liquidity found for pair {}/{} on any venue", token_in, token_out);
+            bail!("No liquidity found for pair {}/{} in V3 or V2 pools", token_in, token_out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::hex;
+    use crate::utils::chains::MAINNET;
+    use alloy::primitives::address;
+
+    const WETH: Address = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDC: Address = address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const UNI: Address = address!("0x1f9840a85d5aF5bf1D1762F925BDADdC4201F984");
+
+    fn two_hop_route() -> Route {
+        Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![UNI, WETH, USDC],
+            fees: vec![fee_tier(3000), fee_tier(500)],
+        }
+    }
+
+    #[test]
+    fn test_encode_path_single_hop() {
+        let route = Route {
+            venue: Venue::UniswapV3,
+            tokens: vec![WETH, USDC],
+            fees: vec![fee_tier(500)],
+        };
+        let path = route.encode_path();
+        assert_eq!(path.len(), 20 + 3 + 20);
+        assert_eq!(&path[..20], WETH.as_slice());
+        assert_eq!(&path[20..23], &hex!("0001f4"));
+        assert_eq!(&path[23..], USDC.as_slice());
+    }
+
+    #[test]
+    fn test_encode_reversed_path() {
+        let path = two_hop_route().encode_reversed_path();
+        assert_eq!(path.len(), 20 * 3 + 3 * 2);
+        assert_eq!(&path[..20], USDC.as_slice());
+        assert_eq!(&path[20..23], &hex!("0001f4"));
+        assert_eq!(&path[23..43], WETH.as_slice());
+        assert_eq!(&path[43..46], &hex!("000bb8"));
+        assert_eq!(&path[46..], UNI.as_slice());
+    }
+
+    #[test]
+    fn test_route_hops() {
+        let hops = two_hop_route().hops();
+        assert_eq!(hops.len(), 2);
+        assert_eq!(hops[0].token_in, UNI);
+        assert_eq!(hops[0].token_out, WETH);
+        assert_eq!(hops[0].fee, 3000);
+        assert_eq!(hops[1].token_in, WETH);
+        assert_eq!(hops[1].token_out, USDC);
+        assert_eq!(hops[1].fee, 500);
+    }
+
+    #[test]
+    fn test_combine_routes() {
+        let routes = combine_routes(
+            UNI,
+            USDC,
+            &[fee_tier(3000)],
+            &[
+                (WETH, vec![fee_tier(500), fee_tier(3000)], vec![fee_tier(500)]),
+                // No pool for the second hop: contributes no routes.
+                (address!("0x6B175474E89094C44Da98b954EedeAC495271d0F"), vec![fee_tier(100)], vec![]),
+            ],
+        );
+
+        assert_eq!(routes.len(), 3);
+        assert!(routes[0].is_single_hop());
+        assert!(routes[1..].iter().all(|r| r.tokens == vec![UNI, WETH, USDC]));
+    }
+
+    #[test]
+    fn test_candidate_v2_routes() {
+        let routes = candidate_v2_routes(&MAINNET, UNI, USDC);
+        // Direct plus every intermediate except USDC itself, on each venue.
+        let per_venue = 1 + MAINNET.route_tokens.len() - 1;
+        assert_eq!(routes.len(), per_venue * MAINNET.v2_venues.len());
+        assert!(routes.iter().all(|r| r.hops().iter().all(|h| h.fee == V2_FEE)));
+        assert_eq!(routes[0].venue.name(), "uniswap_v2");
+    }
+
+    #[test]
+    fn test_quote_calls_by_venue_and_side() {
+        let v3 = quote_call(&MAINNET, &two_hop_route(), QuoteSide::ExactOutput, U256::from(1000));
+        assert_eq!(v3.target, MAINNET.v3_quoter);
+        let expected = V3Quote::ExactOutput {
+            path: two_hop_route().encode_reversed_path(),
+            amount_out: U256::from(1000),
+        };
+        assert_eq!(v3, expected.call(&MAINNET));
+
+        let route = Route::v2(&MAINNET.v2_venues[0], vec![UNI, WETH, USDC]);
+        let v2 = quote_call(&MAINNET, &route, QuoteSide::ExactOutput, U256::from(1000));
+        assert_eq!(v2.target, MAINNET.v2_venues[0].router);
+        assert_eq!(v2.data[..4], UniswapV2Router::getAmountsInCall::SELECTOR);
+
+        let amounts = vec![U256::from(7), U256::from(5), U256::from(1000)];
+        let output = UniswapV2Router::getAmountsInCall::abi_encode_returns(&amounts);
+        let quote = decode_quote(&MAINNET, &route, QuoteSide::ExactOutput, U256::from(1000), &output);
+        assert_eq!(quote.unwrap(), U256::from(7));
+        let output = UniswapV2Router::getAmountsOutCall::abi_encode_returns(&amounts);
+        let quote = decode_quote(&MAINNET, &route, QuoteSide::ExactInput, U256::from(7), &output);
+        assert_eq!(quote.unwrap(), U256::from(1000));
+    }
+
+    #[test]
+    fn test_select_best_tracks_each_venue() {
+        let v3 = two_hop_route();
+        let v2 = Route::v2(&MAINNET.v2_venues[0], vec![UNI, USDC]);
+        let sushi = Route::v2(&MAINNET.v2_venues[1], vec![UNI, USDC]);
+        let search = select_best(
+            vec![v3.clone(), v2.clone(), v2, sushi],
+            vec![
+                Ok(U256::from(100)),
+                Ok(U256::from(90)),
+                Ok(U256::from(120)),
+                Err(anyhow::anyhow!("reverted")),
+            ],
+            |a, b| a > b,
+        )
+        .unwrap();
+
+        assert_eq!(search.best.amount, U256::from(120));
+        assert_eq!(search.best.route.venue.name(), "uniswap_v2");
+        assert_eq!(search.venues.len(), 2);
+        assert_eq!(search.venues[0].route, v3);
+    }
+
+    #[test]
+    fn test_select_best_exact_output_prefers_smaller_input() {
+        let v3 = two_hop_route();
+        let v2 = Route::v2(&MAINNET.v2_venues[0], vec![UNI, USDC]);
+        let search = select_best(
+            vec![v3, v2],
+            vec![Ok(U256::from(100)), Ok(U256::from(90))],
+            |a, b| a < b,
+        )
+        .unwrap();
+        assert_eq!(search.best.amount, U256::from(90));
+    }
+
+    #[test]
+    fn test_select_best_without_liquidity() {
+        let search = select_best(vec![two_hop_route()], vec![Ok(U256::ZERO)], |a, b| a > b);
+        assert!(search.is_none());
+    }
+}
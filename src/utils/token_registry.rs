@@ -1,14 +1,120 @@
-use alloy::primitives::Address;
-use anyhow::{Context, Result};
+use alloy::eips::BlockId;
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, U256, keccak256};
+use alloy::providers::Provider;
+use alloy::sol_types::SolCall;
+use anyhow::{Context, Result, bail};
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
 use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
 use tokio::sync::OnceCell;
 
-use crate::utils::provider::CHAIN_ID;
+use crate::utils::contracts::IERC20;
+use crate::utils::multicall::{Call, aggregate, decode};
 
-static TOKEN_REGISTRY: OnceCell<HashMap<String, Address>> = OnceCell::const_new();
+static TOKEN_REGISTRY: OnceCell<Registry> = OnceCell::const_new();
+static TOKEN_LIST_CONFIG: OnceLock<TokenListConfig> = OnceLock::new();
+// Metadata read on chain for tokens missing from the lists (or listed without decimals).
+static ONCHAIN_METADATA: LazyLock<Mutex<HashMap<(u64, Address), TokenMetadata>>> =
+    LazyLock::new(Default::default);
 
-const UNISWAP_TOKEN_LIST_URL: &str = "https://tokens.uniswap.org";
+pub const UNISWAP_TOKEN_LIST_URL: &str = "https://tokens.uniswap.org";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Token list compiled into the binary, used offline and whenever the remote list is unavailable.
+const BUNDLED_TOKEN_LIST: &str = include_str!("../../tokens/tokenlist.json");
+
+/// A token list in the Uniswap token list schema, fetched from a URL or read from a local file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenListSource {
+    Url(String),
+    File(PathBuf),
+}
+
+impl FromStr for TokenListSource {
+    type Err = std::convert::Infallible;
+
+    fn from_str(source: &str) -> std::result::Result<Self, Self::Err> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            Ok(Self::Url(source.to_string()))
+        } else {
+            Ok(Self::File(PathBuf::from(source)))
+        }
+    }
+}
+
+impl fmt::Display for TokenListSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Url(url) => f.write_str(url),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Where the token lists come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenListConfig {
+    /// Token lists in precedence order, highest first.
+    pub sources: Vec<TokenListSource>,
+    /// User-defined token list taking precedence over every other list.
+    pub overrides: Option<PathBuf>,
+    /// Never fetch remote lists; resolve symbols from local files and the bundled list only.
+    pub offline: bool,
+    /// Directory holding downloaded lists and their ETags; `None` disables the disk cache.
+    pub cache_dir: Option<PathBuf>,
+    /// Age after which a cached list is revalidated against the remote one.
+    pub ttl: Duration,
+}
+
+impl Default for TokenListConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![TokenListSource::Url(UNISWAP_TOKEN_LIST_URL.to_string())],
+            overrides: None,
+            offline: false,
+            cache_dir: None,
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// Set the token list configuration. Must be called before the first symbol lookup to take effect.
+pub fn configure(config: TokenListConfig) {
+    if TOKEN_LIST_CONFIG.set(config).is_err() {
+        tracing::warn!("Token list already configured; ignoring new configuration");
+    }
+}
+
+/// Default token list cache directory: `$XDG_CACHE_HOME/eth_mcp_server` or `~/.cache/eth_mcp_server`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("eth_mcp_server"))
+}
+
+/// Where token metadata read on chain is attributed to.
+pub const ONCHAIN_SOURCE: &str = "onchain";
+
+/// Metadata of one token, from the highest-precedence list that has it or read on chain.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TokenMetadata {
+    pub chain_id: u64,
+    pub address: Address,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+    /// Token list the metadata comes from, or 'onchain'.
+    pub source: String,
+}
 
 #[derive(serde::Deserialize)]
 struct TokenInfo {
@@ -16,6 +122,13 @@ struct TokenInfo {
     chain_id: u64,
     address: String,
     symbol: String,
+    #[serde(default)]
+    name: Option<String>,
+    // Required by the token list schema, but optional in hand-written overrides.
+    #[serde(default)]
+    decimals: Option<u8>,
+    #[serde(default, rename = "logoURI")]
+    logo_uri: Option<String>,
 }
 
 #[derive(serde::Deserialize)]
@@ -23,39 +136,318 @@ struct TokenList {
     tokens: Vec<TokenInfo>,
 }
 
+impl TokenList {
+    fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Failed to parse token list")
+    }
+
+    fn bundled() -> Self {
+        Self::parse(BUNDLED_TOKEN_LIST).expect("bundled token list is valid")
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .context(format!("Failed to read token list {}", path.display()))?;
+        Self::parse(&json).context(format!("Invalid token list {}", path.display()))
+    }
+
+    fn into_tokens(self) -> TokenMap {
+        let mut tokens = TokenMap::new();
+        for token in self.tokens.iter() {
+            if let Ok(address) = Address::from_str(&token.address) {
+                let candidates: &mut Vec<Address> = tokens
+                    .entry(token.chain_id)
+                    .or_default()
+                    .entry(token.symbol.to_uppercase())
+                    .or_default();
+                if !candidates.contains(&address) {
+                    candidates.push(address);
+                }
+            }
+        }
+        tokens
+    }
+}
+
+// Symbol -> every address listed under it, grouped by chain id.
+type TokenMap = HashMap<u64, HashMap<String, Vec<Address>>>;
+
+/// A token as listed, before any on-chain lookup.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ListedToken {
+    pub chain_id: u64,
+    pub address: Address,
+    pub symbol: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    /// Token list the token was found in.
+    pub source: String,
+}
+
+/// Loaded token lists in precedence order, highest first.
+#[derive(Default)]
+struct Registry {
+    lists: Vec<(String, TokenMap)>,
+    // Every listed token in precedence order, for search.
+    tokens: Vec<ListedToken>,
+    // Listed metadata with decimals, keyed by (chain id, address); higher-precedence lists win.
+    metadata: HashMap<(u64, Address), TokenMetadata>,
+}
+
+impl Registry {
+    /// Append a list with lower precedence than every list added before.
+    fn add_list(&mut self, name: String, list: TokenList) {
+        for token in &list.tokens {
+            let Ok(address) = Address::from_str(&token.address) else {
+                continue;
+            };
+            self.tokens.push(ListedToken {
+                chain_id: token.chain_id,
+                address,
+                symbol: token.symbol.clone(),
+                name: token.name.clone().unwrap_or_else(|| token.symbol.clone()),
+                decimals: token.decimals,
+                source: name.clone(),
+            });
+            let Some(decimals) = token.decimals else {
+                continue;
+            };
+            self.metadata
+                .entry((token.chain_id, address))
+                .or_insert_with(|| TokenMetadata {
+                    chain_id: token.chain_id,
+                    address,
+                    symbol: token.symbol.clone(),
+                    name: token.name.clone().unwrap_or_else(|| token.symbol.clone()),
+                    decimals,
+                    logo_uri: token.logo_uri.clone(),
+                    source: name.clone(),
+                });
+        }
+        self.lists.push((name, list.into_tokens()));
+    }
+
+    /// Look `symbol` up in the highest-precedence list that has it on `chain_id`.
+    /// Fails when that list maps the symbol to more than one token.
+    fn lookup(&self, symbol: &str, chain_id: u64) -> Result<Option<Address>> {
+        let symbol_upper = symbol.to_uppercase();
+        for (name, tokens) in &self.lists {
+            let candidates = tokens.get(&chain_id).and_then(|tokens| tokens.get(&symbol_upper));
+            let Some(candidates) = candidates else {
+                continue;
+            };
+            if let [address] = candidates.as_slice() {
+                tracing::trace!("Token {} found in {}", symbol, name);
+                return Ok(Some(*address));
+            }
+            let candidates: Vec<String> = candidates.iter().map(Address::to_string).collect();
+            bail!(
+                "Token symbol '{}' is ambiguous on chain {} in {}: candidates are {}; pass the token address instead",
+                symbol,
+                chain_id,
+                name,
+                candidates.join(", ")
+            );
+        }
+        Ok(None)
+    }
+}
+
 // Static initialization of the token registry.
-async fn get_registry() -> Result<&'static HashMap<String, Address>> {
+async fn get_registry() -> Result<&'static Registry> {
     TOKEN_REGISTRY
         .get_or_try_init(|| async {
-            tracing::debug!("Fetching token list from: {}", UNISWAP_TOKEN_LIST_URL);
-            let client = reqwest::Client::new();
-            let response = client
-                .get(UNISWAP_TOKEN_LIST_URL)
-                .send()
-                .await
-                .context("Failed to fetch token list")?;
-
-            tracing::trace!("Parsing token list");
-            let token_list: TokenList = response
-                .json()
-                .await
-                .context("Failed to parse token list")?;
-
-            let mut registry = HashMap::new();
-            for token in token_list.tokens.iter().filter(|t| t.chain_id == CHAIN_ID) {
-                if let Ok(address) = Address::from_str(&token.address) {
-                    registry.insert(token.symbol.to_uppercase(), address);
-                }
-            }
+            let config = TOKEN_LIST_CONFIG.get().cloned().unwrap_or_default();
+            let registry = load_registry(&config).await?;
 
-            tracing::info!("Token registry initialized with {} tokens for chain {}", registry.len(), CHAIN_ID);
+            tracing::info!(
+                "Token registry initialized from {} lists with {} tokens",
+                registry.lists.len(),
+                registry
+                    .lists
+                    .iter()
+                    .flat_map(|(_, tokens)| tokens.values())
+                    .map(HashMap::len)
+                    .sum::<usize>()
+            );
 
             Ok::<_, anyhow::Error>(registry)
         })
         .await
 }
 
-pub async fn resolve_token(token: &str) -> Result<Address> {
+/// Load the overrides, every configured list and finally the bundled list, in precedence order.
+/// Lists that cannot be loaded are skipped; a broken overrides file is an error.
+async fn load_registry(config: &TokenListConfig) -> Result<Registry> {
+    let mut registry = Registry::default();
+    if let Some(path) = &config.overrides {
+        let overrides = TokenList::read(path).context("Failed to load token overrides")?;
+        registry.add_list(format!("overrides {}", path.display()), overrides);
+    }
+
+    let cache_dir = config.cache_dir.as_deref();
+    for source in &config.sources {
+        match load_token_list(source, config.offline, cache_dir, config.ttl).await {
+            Ok(Some(list)) => registry.add_list(source.to_string(), list),
+            Ok(None) => tracing::debug!("Offline mode: skipping token list {}", source),
+            Err(e) => tracing::warn!("Skipping token list {}: {:#}", source, e),
+        }
+    }
+
+    registry.add_list("bundled token list".to_string(), TokenList::bundled());
+    Ok(registry)
+}
+
+/// Load one token list; remote lists are skipped offline and fall back to a stale cache.
+async fn load_token_list(
+    source: &TokenListSource,
+    offline: bool,
+    cache_dir: Option<&Path>,
+    ttl: Duration,
+) -> Result<Option<TokenList>> {
+    let url = match source {
+        TokenListSource::File(path) => return TokenList::read(path).map(Some),
+        TokenListSource::Url(_) if offline => return Ok(None),
+        TokenListSource::Url(url) => url,
+    };
+
+    let cache = cache_dir.map(|dir| TokenListCache::new(dir, url));
+    match fetch_token_list(url, cache.as_ref(), ttl).await {
+        Ok(list) => Ok(Some(list)),
+        Err(e) => match cache.as_ref().and_then(|cache| cache.read().ok()) {
+            Some(list) => {
+                tracing::warn!("{:#}; using stale cached token list", e);
+                Ok(Some(list))
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Return the cached list while it is younger than `ttl`; otherwise revalidate it with the remote
+/// list's ETag, downloading and caching a new copy when it changed.
+async fn fetch_token_list(url: &str, cache: Option<&TokenListCache>, ttl: Duration) -> Result<TokenList> {
+    if let Some(cache) = cache
+        && cache.is_fresh(ttl)
+    {
+        tracing::debug!("Using cached token list: {}", cache.list_path().display());
+        return cache.read();
+    }
+
+    tracing::debug!("Fetching token list from: {}", url);
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .context("Failed to build HTTP client")?;
+    let mut request = client.get(url);
+    if let Some(etag) = cache.and_then(TokenListCache::etag) {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context(format!("Failed to fetch token list {}", url))?;
+
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let Some(cache) = cache
+    {
+        tracing::debug!("Token list not modified; refreshing cache timestamp");
+        let list = cache.read()?;
+        cache.touch()?;
+        return Ok(list);
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.context("Failed to read token list")?;
+
+    tracing::trace!("Parsing token list");
+    let list = TokenList::parse(&body)?;
+    if let Some(cache) = cache {
+        cache
+            .write(&body, etag.as_deref())
+            .inspect_err(|e| tracing::warn!("Failed to cache token list: {:#}", e))
+            .ok();
+    }
+    Ok(list)
+}
+
+/// A downloaded token list and its ETag stored on disk, named after a hash of the list's URL.
+struct TokenListCache {
+    dir: PathBuf,
+    stem: String,
+}
+
+impl TokenListCache {
+    fn new(dir: &Path, url: &str) -> Self {
+        let hash = keccak256(url.as_bytes());
+        Self {
+            dir: dir.to_path_buf(),
+            stem: format!("tokenlist-{}", alloy::hex::encode(&hash[..8])),
+        }
+    }
+
+    fn list_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.json", self.stem))
+    }
+
+    fn etag_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.etag", self.stem))
+    }
+
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        std::fs::metadata(self.list_path())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age < ttl)
+    }
+
+    fn etag(&self) -> Option<String> {
+        // Without the list itself a 304 response would leave nothing to use.
+        if !self.list_path().exists() {
+            return None;
+        }
+        std::fs::read_to_string(self.etag_path())
+            .ok()
+            .map(|etag| etag.trim().to_string())
+            .filter(|etag| !etag.is_empty())
+    }
+
+    fn read(&self) -> Result<TokenList> {
+        TokenList::read(&self.list_path())
+    }
+
+    fn write(&self, json: &str, etag: Option<&str>) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .context(format!("Failed to create cache directory {}", self.dir.display()))?;
+        std::fs::write(self.list_path(), json).context("Failed to write cached token list")?;
+        match etag {
+            Some(etag) => std::fs::write(self.etag_path(), etag),
+            None => std::fs::remove_file(self.etag_path()).or_else(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(e),
+            }),
+        }
+        .context("Failed to write cached token list ETag")
+    }
+
+    /// Mark the cached list as just revalidated.
+    fn touch(&self) -> Result<()> {
+        std::fs::File::options()
+            .append(true)
+            .open(self.list_path())
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .context("Failed to refresh cached token list timestamp")
+    }
+}
+
+pub async fn resolve_token(token: &str, chain_id: u64) -> Result<Address> {
     // If the token is already an address, return it.
     if token.starts_with("0x") {
         tracing::trace!("Token is already an address: {}", token);
@@ -64,27 +456,216 @@ pub async fn resolve_token(token: &str) -> Result<Address> {
 
     tracing::trace!("Fetching token registry");
     let registry = get_registry().await?;
-    let symbol_upper = token.to_uppercase();
 
-    tracing::debug!("Resolving token symbol: {} -> {}", token, symbol_upper);
-    let result = registry
-        .get(&symbol_upper)
-        .copied()
-        .context(format!("Token symbol '{}' not found in registry", token))?;
+    tracing::debug!("Resolving token symbol: {}", token);
+    let result = registry.lookup(token, chain_id)?.context(format!(
+        "Token symbol '{}' not found in registry for chain {}",
+        token, chain_id
+    ))?;
     tracing::debug!("Resolved token: {} -> {}", token, result.to_string());
     Ok(result)
 }
 
+/// Fuzzy-search the loaded lists by symbol and name, best matches first. Each token is reported
+/// once, from the highest-precedence list that has it; `chain_id` restricts the search to one chain.
+pub async fn search_tokens(
+    query: &str,
+    chain_id: Option<u64>,
+    limit: usize,
+) -> Result<Vec<ListedToken>> {
+    let registry = get_registry().await?;
+    let query = query.trim().to_uppercase();
+    if query.is_empty() {
+        bail!("Search query is empty");
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut matches: Vec<(u32, &ListedToken)> = registry
+        .tokens
+        .iter()
+        .filter(|token| chain_id.is_none_or(|chain_id| token.chain_id == chain_id))
+        .filter(|token| seen.insert((token.chain_id, token.address)))
+        .filter_map(|token| match_score(&query, token).map(|score| (score, token)))
+        .collect();
+    // Stable sort keeps list precedence among equally good matches.
+    matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    tracing::debug!("Token search '{}' matched {} tokens", query, matches.len());
+
+    Ok(matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, token)| token.clone())
+        .collect())
+}
+
+/// How well `token` matches an uppercased query, or `None` when it does not match at all.
+fn match_score(query: &str, token: &ListedToken) -> Option<u32> {
+    let symbol = token.symbol.to_uppercase();
+    let name = token.name.to_uppercase();
+    let score = if symbol == query {
+        100
+    } else if symbol.starts_with(query) {
+        80
+    } else if name == query {
+        70
+    } else if symbol.contains(query) {
+        60
+    } else if name.starts_with(query) {
+        50
+    } else if name.contains(query) {
+        40
+    } else if query.len() >= 3 && edit_distance(&symbol, query) == 1 {
+        // Typos such as 'USCD' or 'WEHT'.
+        30
+    } else if is_subsequence(query, &symbol) {
+        20
+    } else {
+        return None;
+    };
+    Some(score)
+}
+
+/// Levenshtein distance where swapping two adjacent characters also counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Every token listed on `chain_id`, once each, in list precedence order.
+pub async fn listed_tokens(chain_id: u64) -> Result<Vec<ListedToken>> {
+    let registry = get_registry().await?;
+    let mut seen = std::collections::HashSet::new();
+    Ok(registry
+        .tokens
+        .iter()
+        .filter(|token| token.chain_id == chain_id && seen.insert(token.address))
+        .cloned()
+        .collect())
+}
+
+/// Metadata for `address` from the highest-precedence list that gives its decimals.
+pub async fn listed_metadata(chain_id: u64, address: Address) -> Result<Option<TokenMetadata>> {
+    Ok(get_registry().await?.metadata.get(&(chain_id, address)).cloned())
+}
+
+/// Metadata of the token at `address`: taken from the token lists when they give its decimals,
+/// otherwise read on chain with `decimals()`, `symbol()` and `name()` and cached for later calls.
+pub async fn token_metadata(
+    provider: &impl Provider<Ethereum>,
+    chain_id: u64,
+    address: Address,
+) -> Result<TokenMetadata> {
+    let registry = get_registry().await?;
+    if let Some(metadata) = registry.metadata.get(&(chain_id, address)) {
+        tracing::trace!("Token {} metadata from {}", address, metadata.source);
+        return Ok(metadata.clone());
+    }
+    if let Some(metadata) = ONCHAIN_METADATA.lock().unwrap().get(&(chain_id, address)) {
+        tracing::trace!("Token {} metadata from cache", address);
+        return Ok(metadata.clone());
+    }
+
+    tracing::debug!("Fetching metadata for token {} on chain", address);
+    let OnchainMetadata { name, symbol, decimals, .. } = onchain_metadata(provider, address).await?;
+    let decimals = decimals.context(format!("Failed to fetch decimals of token {}", address))?;
+    let metadata = TokenMetadata {
+        chain_id,
+        address,
+        name: name.unwrap_or_else(|| symbol.clone().unwrap_or_default()),
+        symbol: symbol.unwrap_or_default(),
+        decimals,
+        logo_uri: None,
+        source: ONCHAIN_SOURCE.to_string(),
+    };
+    ONCHAIN_METADATA
+        .lock()
+        .unwrap()
+        .insert((chain_id, address), metadata.clone());
+    Ok(metadata)
+}
+
+/// ERC20 metadata read on chain; getters the contract does not implement are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OnchainMetadata {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub total_supply: Option<U256>,
+}
+
+/// Read `name()`, `symbol()`, `decimals()` and `totalSupply()` of the contract at `address` in
+/// one batch.
+pub async fn onchain_metadata(
+    provider: &impl Provider<Ethereum>,
+    address: Address,
+) -> Result<OnchainMetadata> {
+    let calls = [
+        Call::new(address, &IERC20::nameCall {}),
+        Call::new(address, &IERC20::symbolCall {}),
+        Call::new(address, &IERC20::decimalsCall {}),
+        Call::new(address, &IERC20::totalSupplyCall {}),
+    ];
+    let outputs = aggregate(provider, BlockId::latest(), &calls)
+        .await
+        .context(format!("Failed to fetch metadata of token {}", address))?;
+    Ok(OnchainMetadata {
+        name: outputs[0].as_ref().and_then(|output| decode_string(output)),
+        symbol: outputs[1].as_ref().and_then(|output| decode_string(output)),
+        decimals: decode::<IERC20::decimalsCall>(outputs[2].as_ref()),
+        total_supply: decode::<IERC20::totalSupplyCall>(outputs[3].as_ref()),
+    })
+}
+
+/// Decode a metadata getter's `string`, or `bytes32` on older tokens such as MKR.
+fn decode_string(output: &[u8]) -> Option<String> {
+    // `symbol()` and `name()` share the same `string` return type.
+    if let Ok(value) = IERC20::symbolCall::abi_decode_returns(output)
+        && !value.is_empty()
+    {
+        return Some(value);
+    }
+    // A bytes32 value, right-padded with zeros.
+    let bytes = output.get(..32)?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let value = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::chains::ARBITRUM;
     use alloy::primitives::Address;
 
     #[tokio::test]
     async fn test_resolve_token_with_address() {
         // Test with a valid Ethereum address
         let address_str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"; // WETH
-        let result = resolve_token(address_str).await;
+        let result = resolve_token(address_str, 1).await;
         assert!(result.is_ok());
         let address = result.unwrap();
         assert_eq!(address, Address::from_str(address_str).unwrap());
@@ -94,27 +675,23 @@ mod tests {
     async fn test_resolve_token_with_invalid_address() {
         // Test with an invalid address
         let invalid_address = "0xInvalidAddress";
-        let result = resolve_token(invalid_address).await;
+        let result = resolve_token(invalid_address, 1).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_resolve_token_with_symbol() {
-        // Test with a known token symbol (this will make a real HTTP request)
-        // Note: This test requires network access
-        let result = resolve_token("WETH").await;
-        // This might succeed or fail depending on network and registry state
-        // We just check it doesn't panic
+        // Test with a known token symbol (falls back to the bundled list without network access)
+        let result = resolve_token("WETH", 1).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_resolve_token_case_insensitive() {
         // Test that symbol resolution is case-insensitive
-        // Note: This test requires network access
-        let result_lower = resolve_token("weth").await;
-        let result_upper = resolve_token("WETH").await;
-        let result_mixed = resolve_token("WeTh").await;
+        let result_lower = resolve_token("weth", 1).await;
+        let result_upper = resolve_token("WETH", 1).await;
+        let result_mixed = resolve_token("WeTh", 1).await;
 
         // If both succeed, they should return the same address
         if let (Ok(addr_lower), Ok(addr_upper)) = (&result_lower, &result_upper) {
@@ -128,11 +705,290 @@ mod tests {
     #[tokio::test]
     async fn test_resolve_token_unknown_symbol() {
         // Test with an unknown symbol
-        let result = resolve_token("UNKNOWNSYMBOL123").await;
+        let result = resolve_token("UNKNOWNSYMBOL123", 1).await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("not found"));
     }
 
+    #[tokio::test]
+    async fn test_resolve_token_filters_by_chain() {
+        let mainnet = resolve_token("USDC", 1).await;
+        let arbitrum = resolve_token("USDC", 42161).await;
+        if let (Ok(mainnet), Ok(arbitrum)) = (&mainnet, &arbitrum) {
+            assert_ne!(mainnet, arbitrum);
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("eth_mcp_server_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn token_list(tokens: &[(u64, &str, &str)]) -> String {
+        let tokens: Vec<_> = tokens
+            .iter()
+            .map(|(chain_id, symbol, address)| {
+                serde_json::json!({ "chainId": chain_id, "address": address, "symbol": symbol })
+            })
+            .collect();
+        serde_json::json!({ "tokens": tokens }).to_string()
+    }
+
+    const TOKEN_A: &str = "0x0000000000000000000000000000000000000001";
+    const TOKEN_B: &str = "0x0000000000000000000000000000000000000002";
+
+    #[test]
+    fn test_bundled_token_list_covers_supported_chains() {
+        let tokens = TokenList::bundled().into_tokens();
+        for chain in crate::utils::chains::CHAIN_PROFILES {
+            if chain.chain_id == crate::utils::chains::LOCAL.chain_id {
+                continue;
+            }
+            let tokens = tokens.get(&chain.chain_id).expect("chain is bundled");
+            let wrapped = tokens.values().any(|candidates| candidates == &vec![chain.weth]);
+            assert!(wrapped, "{}", chain.name);
+            assert_eq!(tokens.get("USDC"), Some(&vec![chain.usd_token]), "{}", chain.name);
+        }
+    }
+
+    #[test]
+    fn test_token_list_source_from_str() {
+        assert_eq!(
+            TokenListSource::from_str("https://tokens.coingecko.com/uniswap/all.json").unwrap(),
+            TokenListSource::Url("https://tokens.coingecko.com/uniswap/all.json".to_string())
+        );
+        assert_eq!(
+            TokenListSource::from_str("./lists/mine.json").unwrap(),
+            TokenListSource::File(PathBuf::from("./lists/mine.json"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_offline_skips_remote_lists() {
+        let config = TokenListConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let registry = load_registry(&config).await.unwrap();
+        assert_eq!(registry.lists.len(), 1);
+        assert_eq!(
+            registry.lookup("weth", 1).unwrap(),
+            Some(Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_lists_follow_precedence() {
+        let dir = temp_dir("precedence");
+        std::fs::create_dir_all(&dir).unwrap();
+        let overrides = dir.join("overrides.json");
+        let first = dir.join("first.json");
+        let second = dir.join("second.json");
+        std::fs::write(&overrides, token_list(&[(1, "WETH", TOKEN_A)])).unwrap();
+        std::fs::write(&first, token_list(&[(1, "FOO", TOKEN_A)])).unwrap();
+        std::fs::write(&second, token_list(&[(1, "FOO", TOKEN_B), (1, "BAR", TOKEN_B)])).unwrap();
+
+        let config = TokenListConfig {
+            sources: vec![TokenListSource::File(first), TokenListSource::File(second)],
+            overrides: Some(overrides),
+            offline: true,
+            ..Default::default()
+        };
+        let registry = load_registry(&config).await.unwrap();
+        let address = |token: &str| Some(Address::from_str(token).unwrap());
+        assert_eq!(registry.lookup("WETH", 1).unwrap(), address(TOKEN_A));
+        assert_eq!(registry.lookup("FOO", 1).unwrap(), address(TOKEN_A));
+        assert_eq!(registry.lookup("BAR", 1).unwrap(), address(TOKEN_B));
+        // The bundled list comes last.
+        let usdt = address("0xdAC17F958D2ee523a2206206994597C13D831ec7");
+        assert_eq!(registry.lookup("USDT", 1).unwrap(), usdt);
+        assert_eq!(registry.lookup("BAR", 10).unwrap(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_overrides_file_is_an_error() {
+        let config = TokenListConfig {
+            overrides: Some(temp_dir("missing_overrides").join("overrides.json")),
+            offline: true,
+            ..Default::default()
+        };
+        assert!(load_registry(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_list_is_skipped() {
+        let config = TokenListConfig {
+            sources: vec![TokenListSource::File(temp_dir("missing_list").join("list.json"))],
+            offline: true,
+            ..Default::default()
+        };
+        let registry = load_registry(&config).await.unwrap();
+        assert_eq!(registry.lists.len(), 1);
+    }
+
+    #[test]
+    fn test_lookup_reports_ambiguous_symbol() {
+        let list = token_list(&[
+            (1, "FOO", TOKEN_A),
+            (1, "foo", TOKEN_B),
+            (1, "BAR", TOKEN_A),
+            (1, "BAR", TOKEN_A),
+        ]);
+        let mut registry = Registry::default();
+        registry.add_list("test list".to_string(), TokenList::parse(&list).unwrap());
+        let err = registry.lookup("foo", 1).unwrap_err().to_string();
+        assert!(err.contains("ambiguous"));
+        assert!(err.contains(&Address::from_str(TOKEN_A).unwrap().to_string()));
+        assert!(err.contains(&Address::from_str(TOKEN_B).unwrap().to_string()));
+        // The same address listed twice is not ambiguous.
+        assert_eq!(registry.lookup("BAR", 1).unwrap(), Some(Address::from_str(TOKEN_A).unwrap()));
+    }
+
+    #[test]
+    fn test_registry_keeps_metadata_of_highest_precedence_list() {
+        let first = r#"{"tokens":[{"chainId":1,"address":"0x0000000000000000000000000000000000000001","symbol":"FOO","name":"Foo","decimals":6,"logoURI":"https://example.com/foo.png"}]}"#;
+        let second = r#"{"tokens":[{"chainId":1,"address":"0x0000000000000000000000000000000000000001","symbol":"FOO2","decimals":18},{"chainId":1,"address":"0x0000000000000000000000000000000000000002","symbol":"BAR"}]}"#;
+        let mut registry = Registry::default();
+        registry.add_list("first".to_string(), TokenList::parse(first).unwrap());
+        registry.add_list("second".to_string(), TokenList::parse(second).unwrap());
+
+        let foo = &registry.metadata[&(1, Address::from_str(TOKEN_A).unwrap())];
+        assert_eq!(foo.symbol, "FOO");
+        assert_eq!(foo.name, "Foo");
+        assert_eq!(foo.decimals, 6);
+        assert_eq!(foo.logo_uri.as_deref(), Some("https://example.com/foo.png"));
+        assert_eq!(foo.source, "first");
+        // Listed without decimals, so left to the on-chain lookup.
+        assert!(!registry.metadata.contains_key(&(1, Address::from_str(TOKEN_B).unwrap())));
+    }
+
+    fn listed(symbol: &str, name: &str) -> ListedToken {
+        ListedToken {
+            chain_id: 1,
+            address: Address::ZERO,
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            decimals: None,
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_match_score_ranks_symbol_over_name() {
+        let usdc = listed("USDC", "USDCoin");
+        assert_eq!(match_score("USDC", &usdc), Some(100));
+        assert_eq!(match_score("USD", &usdc), Some(80));
+        assert_eq!(match_score("USDCOIN", &usdc), Some(70));
+        assert_eq!(match_score("SDC", &usdc), Some(60));
+        assert_eq!(match_score("USCD", &usdc), Some(30));
+        assert_eq!(match_score("UC", &usdc), Some(20));
+        assert_eq!(match_score("DAI", &usdc), None);
+
+        let uni = listed("UNI", "Uniswap");
+        assert_eq!(match_score("UNISWAP", &uni), Some(70));
+        assert_eq!(match_score("SWAP", &uni), Some(40));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("WETH", "WETH"), 0);
+        assert_eq!(edit_distance("WETH", "WEHT"), 1);
+        assert_eq!(edit_distance("USDC", "USDT"), 1);
+        assert_eq!(edit_distance("USDC", "DAI"), 4);
+    }
+
+    #[tokio::test]
+    async fn test_search_tokens_in_bundled_list() {
+        let results = search_tokens("usd", Some(1), 10).await.unwrap();
+        let symbols: Vec<&str> = results.iter().map(|token| token.symbol.as_str()).collect();
+        assert!(symbols.contains(&"USDC"));
+        assert!(symbols.contains(&"USDT"));
+        assert!(results.iter().all(|token| token.chain_id == 1));
+
+        let results = search_tokens("WETH", None, 50).await.unwrap();
+        assert!(results.iter().any(|token| token.chain_id == 42161));
+        assert_eq!(results[0].symbol, "WETH");
+
+        assert!(search_tokens("  ", None, 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_listed_tokens_are_unique_per_chain() {
+        let tokens = listed_tokens(42161).await.unwrap();
+        assert!(tokens.iter().any(|token| token.address == ARBITRUM.usd_token));
+        assert!(tokens.iter().all(|token| token.chain_id == 42161));
+
+        let mut addresses: Vec<Address> = tokens.iter().map(|token| token.address).collect();
+        addresses.sort();
+        addresses.dedup();
+        assert_eq!(addresses.len(), tokens.len());
+    }
+
+    #[test]
+    fn test_decode_string_handles_string_and_bytes32() {
+        let dai = IERC20::nameCall::abi_encode_returns(&"Dai Stablecoin".to_string());
+        assert_eq!(decode_string(&dai).as_deref(), Some("Dai Stablecoin"));
+        // MKR returns its symbol as bytes32.
+        let mut mkr = [0u8; 32];
+        mkr[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_string(&mkr).as_deref(), Some("MKR"));
+        assert_eq!(decode_string(&[0u8; 32]), None);
+        assert_eq!(decode_string(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_fresh_cache_is_used_without_fetching() {
+        let dir = temp_dir("fresh_cache");
+        let url = "https://example.invalid/tokens.json";
+        let cache = TokenListCache::new(&dir, url);
+        cache.write(&token_list(&[(1, "CACHED", TOKEN_A)]), Some("\"abc\"")).unwrap();
+        assert!(cache.is_fresh(Duration::from_secs(60)));
+        assert!(!cache.is_fresh(Duration::ZERO));
+        assert_eq!(cache.etag().as_deref(), Some("\"abc\""));
+
+        let list = fetch_token_list(url, Some(&cache), Duration::from_secs(60)).await.unwrap();
+        assert!(list.into_tokens()[&1].contains_key("CACHED"));
+
+        // An unreachable list falls back to its stale cache.
+        let source = TokenListSource::Url(url.to_string());
+        let list = load_token_list(&source, false, Some(&dir), Duration::ZERO).await.unwrap();
+        assert!(list.is_some());
+
+        cache.write(&token_list(&[]), None).unwrap();
+        assert_eq!(cache.etag(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_files_are_named_per_url() {
+        let dir = Path::new("/tmp");
+        let uniswap = TokenListCache::new(dir, UNISWAP_TOKEN_LIST_URL);
+        let other = TokenListCache::new(dir, "https://example.invalid/tokens.json");
+        assert_ne!(uniswap.list_path(), other.list_path());
+        assert_eq!(uniswap.list_path().extension().unwrap(), "json");
+    }
+
+    #[test]
+    fn test_cache_touch_refreshes_age() {
+        let dir = temp_dir("touch");
+        let cache = TokenListCache::new(&dir, UNISWAP_TOKEN_LIST_URL);
+        cache.write(&token_list(&[]), None).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        std::fs::File::options()
+            .append(true)
+            .open(cache.list_path())
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert!(!cache.is_fresh(Duration::from_secs(60)));
+
+        cache.touch().unwrap();
+        assert!(cache.is_fresh(Duration::from_secs(60)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_address_parsing() {
         // Test that we can parse valid addresses
@@ -0,0 +1,352 @@
+use alloy::dyn_abi::TypedData;
+use alloy::dyn_abi::eip712::Resolver;
+use alloy::primitives::aliases::{U48, U160};
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, address, keccak256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, SolValue, eip712_domain};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::utils::chains::{ChainProfile, PERMIT2};
+use crate::utils::contracts::IAllowanceTransfer::{PermitDetails, PermitSingle};
+use crate::utils::contracts::UniswapUniversalRouter;
+
+// Universal Router command bytes (see `Commands.sol` in Uniswap/universal-router).
+pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+pub const V3_SWAP_EXACT_OUT: u8 = 0x01;
+pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+pub const V2_SWAP_EXACT_OUT: u8 = 0x09;
+pub const PERMIT2_PERMIT: u8 = 0x0a;
+pub const WRAP_ETH: u8 = 0x0b;
+pub const UNWRAP_WETH: u8 = 0x0c;
+
+/// Recipient placeholder resolved by the router to the caller.
+pub const MSG_SENDER: Address = address!("0x0000000000000000000000000000000000000001");
+/// Recipient placeholder resolved by the router to itself, for intermediate outputs.
+/// `SwapRouter02` uses the same sentinel.
+pub const ADDRESS_THIS: Address = address!("0x0000000000000000000000000000000000000002");
+
+// Permit2's `allowance` mapping lives in slot 1 (`nonceBitmap` of SignatureTransfer takes slot 0).
+const PERMIT2_ALLOWANCE_SLOT: u64 = 1;
+
+/// A Universal Router `execute` call being assembled, one command at a time.
+#[derive(Debug, Clone, Default)]
+pub struct UniversalRouterCall {
+    commands: Vec<u8>,
+    inputs: Vec<Bytes>,
+}
+
+impl UniversalRouterCall {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, command: u8, input: Vec<u8>) -> &mut Self {
+        self.commands.push(command);
+        self.inputs.push(input.into());
+        self
+    }
+
+    /// Approve the router through Permit2 with a signed `PermitSingle`.
+    pub fn permit2_permit(&mut self, permit: PermitSingle, signature: Bytes) -> &mut Self {
+        self.push(PERMIT2_PERMIT, (permit, signature).abi_encode_params())
+    }
+
+    /// Swap exactly `amount_in` along an encoded V3 path.
+    pub fn v3_swap_exact_in(
+        &mut self,
+        recipient: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: Bytes,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_in, amount_out_min, path, payer_is_user).abi_encode_params();
+        self.push(V3_SWAP_EXACT_IN, input)
+    }
+
+    /// Receive exactly `amount_out` along a V3 path encoded output token first.
+    pub fn v3_swap_exact_out(
+        &mut self,
+        recipient: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+        reversed_path: Bytes,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input =
+            (recipient, amount_out, amount_in_max, reversed_path, payer_is_user).abi_encode_params();
+        self.push(V3_SWAP_EXACT_OUT, input)
+    }
+
+    /// Swap exactly `amount_in` through Uniswap V2 pairs along `path`.
+    pub fn v2_swap_exact_in(
+        &mut self,
+        recipient: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: Vec<Address>,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_in, amount_out_min, path, payer_is_user).abi_encode_params();
+        self.push(V2_SWAP_EXACT_IN, input)
+    }
+
+    /// Receive exactly `amount_out` through Uniswap V2 pairs along `path`.
+    pub fn v2_swap_exact_out(
+        &mut self,
+        recipient: Address,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: Vec<Address>,
+        payer_is_user: bool,
+    ) -> &mut Self {
+        let input = (recipient, amount_out, amount_in_max, path, payer_is_user).abi_encode_params();
+        self.push(V2_SWAP_EXACT_OUT, input)
+    }
+
+    /// Wrap `amount` of the ETH sent with the call into WETH held by `recipient`.
+    pub fn wrap_eth(&mut self, recipient: Address, amount: U256) -> &mut Self {
+        self.push(WRAP_ETH, (recipient, amount).abi_encode_params())
+    }
+
+    /// Unwrap the router's whole WETH balance to `recipient`, requiring at least `amount_min`.
+    pub fn unwrap_weth(&mut self, recipient: Address, amount_min: U256) -> &mut Self {
+        self.push(UNWRAP_WETH, (recipient, amount_min).abi_encode_params())
+    }
+
+    /// Encode `execute(commands, inputs, deadline)`.
+    pub fn encode(&self, deadline: U256) -> Bytes {
+        UniswapUniversalRouter::execute_1Call {
+            commands: self.commands.clone().into(),
+            inputs: self.inputs.clone(),
+            deadline,
+        }
+        .abi_encode()
+        .into()
+    }
+}
+
+/// A Permit2 allowance of `amount` of `token` for `spender`, valid until `expiration`.
+pub fn permit_single(
+    token: Address,
+    amount: U256,
+    expiration: u64,
+    nonce: U48,
+    spender: Address,
+    sig_deadline: U256,
+) -> PermitSingle {
+    PermitSingle {
+        details: PermitDetails {
+            token,
+            amount: U160::saturating_from(amount),
+            expiration: U48::saturating_from(expiration),
+            nonce,
+        },
+        spender,
+        sigDeadline: sig_deadline,
+    }
+}
+
+/// EIP-712 domain of Permit2 on `chain`.
+fn permit2_domain(chain: &ChainProfile) -> Eip712Domain {
+    eip712_domain! {
+        name: "Permit2",
+        chain_id: chain.chain_id,
+        verifying_contract: PERMIT2,
+    }
+}
+
+/// Sign `permit` as EIP-712 typed data against the chain's Permit2 domain.
+pub fn sign_permit(
+    signer: &PrivateKeySigner,
+    chain: &ChainProfile,
+    permit: &PermitSingle,
+) -> Result<Bytes> {
+    let signature = signer.sign_hash_sync(&permit.eip712_signing_hash(&permit2_domain(chain)))?;
+    Ok(Bytes::from(signature.as_bytes()))
+}
+
+/// `permit` as the EIP-712 typed data an external signer signs (`eth_signTypedData_v4`).
+pub fn permit_typed_data(chain: &ChainProfile, permit: &PermitSingle) -> TypedData {
+    let details = &permit.details;
+    TypedData {
+        domain: permit2_domain(chain),
+        resolver: Resolver::from_struct::<PermitSingle>(),
+        primary_type: PermitSingle::NAME.to_string(),
+        message: serde_json::json!({
+            "details": {
+                "token": details.token,
+                "amount": details.amount.to_string(),
+                "expiration": details.expiration.to_string(),
+                "nonce": details.nonce.to_string(),
+            },
+            "spender": permit.spender,
+            "sigDeadline": permit.sigDeadline.to_string(),
+        }),
+    }
+}
+
+/// `PermitSingle` fields as they appear in the `message` of [`permit_typed_data`].
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PermitMessage {
+    details: PermitDetailsMessage,
+    spender: Address,
+    sig_deadline: U256,
+}
+
+#[derive(serde::Deserialize)]
+struct PermitDetailsMessage {
+    token: Address,
+    amount: U160,
+    expiration: U48,
+    nonce: U48,
+}
+
+/// Read back the permit from the `message` of [`permit_typed_data`].
+pub fn permit_from_message(message: &serde_json::Value) -> Result<PermitSingle> {
+    let message = PermitMessage::deserialize(message).context("Invalid PermitSingle message")?;
+    let details = message.details;
+    Ok(PermitSingle {
+        details: PermitDetails {
+            token: details.token,
+            amount: details.amount,
+            expiration: details.expiration,
+            nonce: details.nonce,
+        },
+        spender: message.spender,
+        sigDeadline: message.sig_deadline,
+    })
+}
+
+/// Address that signed `permit` on `chain` with `signature`.
+pub fn recover_permit_signer(
+    chain: &ChainProfile,
+    permit: &PermitSingle,
+    signature: &[u8],
+) -> Result<Address> {
+    let signature = Signature::try_from(signature).context("Invalid permit signature")?;
+    signature
+        .recover_address_from_prehash(&permit.eip712_signing_hash(&permit2_domain(chain)))
+        .context("Failed to recover the permit signer")
+}
+
+/// Storage slot of Permit2's `allowance[owner][token][spender]`.
+pub fn permit2_allowance_slot(owner: Address, token: Address, spender: Address) -> B256 {
+    let owner_slot = keccak256((owner, U256::from(PERMIT2_ALLOWANCE_SLOT)).abi_encode());
+    let token_slot = keccak256((token, owner_slot).abi_encode());
+    keccak256((spender, token_slot).abi_encode())
+}
+
+/// Packed `PackedAllowance { amount, expiration, nonce }` granting the maximum amount forever.
+pub fn unlimited_permit2_allowance() -> B256 {
+    // amount: bits 0..160, expiration: bits 160..208, nonce (zero): bits 208..256.
+    let amount = U256::from(U160::MAX);
+    let expiration = U256::from(U48::MAX) << 160;
+    B256::from(amount | expiration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::chains::{ARBITRUM, MAINNET};
+
+    #[test]
+    fn test_encode_collects_commands_in_order() {
+        let recipient = address!("0x1000000000000000000000000000000000000000");
+        let mut call = UniversalRouterCall::new();
+        call.wrap_eth(ADDRESS_THIS, U256::from(1))
+            .v3_swap_exact_in(recipient, U256::from(1), U256::ZERO, Bytes::new(), false);
+
+        let calldata = call.encode(U256::from(42));
+        let decoded = UniswapUniversalRouter::execute_1Call::abi_decode(&calldata).unwrap();
+        assert_eq!(decoded.commands.as_ref(), &[WRAP_ETH, V3_SWAP_EXACT_IN]);
+        assert_eq!(decoded.inputs.len(), 2);
+        assert_eq!(decoded.deadline, U256::from(42));
+    }
+
+    #[test]
+    fn test_v2_swap_exact_in_input_layout() {
+        let recipient = address!("0x1000000000000000000000000000000000000000");
+        let path = vec![MAINNET.weth, address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")];
+        let mut call = UniversalRouterCall::new();
+        call.v2_swap_exact_in(recipient, U256::from(100), U256::from(90), path.clone(), true);
+
+        let decoded = UniswapUniversalRouter::execute_1Call::abi_decode(&call.encode(U256::ZERO)).unwrap();
+        let (to, amount_in, amount_out_min, decoded_path, payer_is_user) =
+            <(Address, U256, U256, Vec<Address>, bool)>::abi_decode_params(&decoded.inputs[0]).unwrap();
+        assert_eq!(to, recipient);
+        assert_eq!(amount_in, U256::from(100));
+        assert_eq!(amount_out_min, U256::from(90));
+        assert_eq!(decoded_path, path);
+        assert!(payer_is_user);
+    }
+
+    #[test]
+    fn test_permit_single_type_hash_matches_permit2() {
+        let permit = permit_single(Address::ZERO, U256::ZERO, 0, U48::ZERO, Address::ZERO, U256::ZERO);
+        assert_eq!(
+            permit.eip712_type_hash(),
+            keccak256(
+                "PermitSingle(PermitDetails details,address spender,uint256 sigDeadline)PermitDetails(address token,uint160 amount,uint48 expiration,uint48 nonce)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_permit_single_saturates_amount() {
+        let permit = permit_single(Address::ZERO, U256::MAX, 0, U48::ZERO, Address::ZERO, U256::ZERO);
+        assert_eq!(permit.details.amount, U160::MAX);
+    }
+
+    #[test]
+    fn test_sign_permit_recovers_signer() {
+        let signer = PrivateKeySigner::random();
+        let permit = permit_single(
+            MAINNET.weth,
+            U256::from(1),
+            1,
+            U48::ZERO,
+            MAINNET.universal_router,
+            U256::from(1),
+        );
+        let signature = sign_permit(&signer, &MAINNET, &permit).unwrap();
+        assert_eq!(signature.len(), 65);
+        assert_eq!(recover_permit_signer(&MAINNET, &permit, &signature).unwrap(), signer.address());
+        assert_ne!(recover_permit_signer(&ARBITRUM, &permit, &signature).unwrap(), signer.address());
+        assert!(recover_permit_signer(&MAINNET, &permit, &signature[..64]).is_err());
+    }
+
+    #[test]
+    fn test_permit_typed_data_hashes_like_the_permit() {
+        let permit = permit_single(
+            MAINNET.usd_token,
+            U256::from(1_000_000),
+            1_700_000_000,
+            U48::from(3),
+            MAINNET.universal_router,
+            U256::from(1_700_000_600),
+        );
+        let typed_data = permit_typed_data(&MAINNET, &permit);
+        assert_eq!(
+            typed_data.eip712_signing_hash().unwrap(),
+            permit.eip712_signing_hash(&permit2_domain(&MAINNET))
+        );
+        let json = serde_json::to_value(&typed_data).unwrap();
+        assert_eq!(json["primaryType"], "PermitSingle");
+        assert_eq!(json["message"]["details"]["nonce"], "3");
+        let parsed = permit_from_message(&json["message"]).unwrap();
+        let domain = permit2_domain(&MAINNET);
+        assert_eq!(parsed.eip712_signing_hash(&domain), permit.eip712_signing_hash(&domain));
+        assert!(permit_from_message(&serde_json::json!({ "spender": MAINNET.universal_router })).is_err());
+    }
+
+    #[test]
+    fn test_unlimited_permit2_allowance_packing() {
+        let packed = U256::from_be_bytes(unlimited_permit2_allowance().0);
+        assert_eq!(packed & U256::from(U160::MAX), U256::from(U160::MAX));
+        assert_eq!(packed >> 208, U256::ZERO);
+    }
+}